@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use valence::prelude::*;
+
+use crate::config::{AntiBotConfig, AntiBotMode};
+
+/// Join verification for first-time IPs and accounts. Players have to
+/// reconnect within a time frame before they are let through, which most
+/// bots don't do. Passing is remembered for both the IP and the account.
+#[derive(Debug)]
+pub struct AntiBot {
+    config: AntiBotConfig,
+    inner: Mutex<AntiBotState>,
+}
+
+#[derive(Debug)]
+struct AntiBotState {
+    /// Start and join count of the current one second window.
+    window_start: Instant,
+    window_joins: u32,
+    /// Verification stays on until this instant after the join rate crossed
+    /// the threshold.
+    attack_until: Option<Instant>,
+    /// First attempt of players asked to reconnect.
+    pending: HashMap<(IpAddr, Uuid), Instant>,
+    verified_ips: HashMap<IpAddr, Instant>,
+    verified_accounts: HashMap<Uuid, Instant>,
+}
+
+impl AntiBot {
+    pub fn new(config: &AntiBotConfig) -> Self {
+        Self {
+            config: config.to_owned(),
+            inner: Mutex::new(AntiBotState {
+                window_start: Instant::now(),
+                window_joins: 0,
+                attack_until: None,
+                pending: HashMap::new(),
+                verified_ips: HashMap::new(),
+                verified_accounts: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Records a join and returns whether the player may continue, asking
+    /// them to reconnect otherwise.
+    pub fn verify(&self, ip: IpAddr, uuid: Uuid) -> bool {
+        let now = Instant::now();
+        let mut lck = self.inner.lock().unwrap();
+
+        if now.duration_since(lck.window_start) >= Duration::from_secs(1) {
+            lck.window_start = now;
+            lck.window_joins = 0;
+        }
+        lck.window_joins += 1;
+
+        let under_attack = lck.attack_until.is_some_and(|until| until > now);
+        if self.config.mode == AntiBotMode::Auto
+            && !under_attack
+            && lck.window_joins > self.config.join_rate_threshold
+        {
+            println!(
+                "Join rate over {} per second, enabling join verification.",
+                self.config.join_rate_threshold
+            );
+            lck.attack_until = Some(now + Duration::from_secs(self.config.active_secs));
+        }
+
+        let active = match self.config.mode {
+            AntiBotMode::Off => false,
+            AntiBotMode::Always => true,
+            AntiBotMode::Auto => lck.attack_until.is_some_and(|until| until > now),
+        };
+
+        if !active
+            || (lck.verified_ips.contains_key(&ip) && lck.verified_accounts.contains_key(&uuid))
+        {
+            return true;
+        }
+
+        let min_delay = Duration::from_millis(self.config.reconnect_min_delay_ms);
+        let timeout = Duration::from_secs(self.config.reconnect_timeout_secs);
+
+        if let Some(first_attempt) = lck.pending.remove(&(ip, uuid)) {
+            let elapsed = now.duration_since(first_attempt);
+            if elapsed >= min_delay && elapsed <= timeout {
+                let verified_until = now + Duration::from_secs(self.config.verified_secs);
+                lck.verified_ips.insert(ip, verified_until);
+                lck.verified_accounts.insert(uuid, verified_until);
+                return true;
+            }
+        }
+
+        lck.pending.insert((ip, uuid), now);
+        false
+    }
+
+    /// Forgets expired verifications and reconnect attempts.
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        let timeout = Duration::from_secs(self.config.reconnect_timeout_secs);
+        let mut lck = self.inner.lock().unwrap();
+
+        lck.pending
+            .retain(|_, first_attempt| now.duration_since(*first_attempt) <= timeout);
+        lck.verified_ips.retain(|_, until| *until > now);
+        lck.verified_accounts.retain(|_, until| *until > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn antibot_reconnect_verification() {
+        let antibot = AntiBot::new(&AntiBotConfig {
+            mode: AntiBotMode::Always,
+            reconnect_min_delay_ms: 0,
+            ..Default::default()
+        });
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let uuid = Uuid::from_u128(1);
+
+        assert!(!antibot.verify(ip, uuid));
+        assert!(antibot.verify(ip, uuid));
+        assert!(antibot.verify(ip, uuid));
+
+        // A new account from a verified IP still has to reconnect.
+        assert!(!antibot.verify(ip, Uuid::from_u128(2)));
+    }
+
+    #[test]
+    fn antibot_auto_mode_threshold() {
+        let antibot = AntiBot::new(&AntiBotConfig {
+            mode: AntiBotMode::Auto,
+            join_rate_threshold: 2,
+            ..Default::default()
+        });
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+
+        assert!(antibot.verify(ip, Uuid::from_u128(1)));
+        assert!(antibot.verify(ip, Uuid::from_u128(2)));
+        assert!(!antibot.verify(ip, Uuid::from_u128(3)));
+    }
+}
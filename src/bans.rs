@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ipnet::IpNet;
+use serde::Deserialize;
+
+use valence::prelude::*;
+
+use crate::utils::{file_modified, parse_ip_net};
+
+/// Entry of the ban file. Exactly one of `uuid`, `name` or `ip` is expected;
+/// `ip` accepts a single address or a CIDR range.
+#[derive(Debug, Deserialize)]
+struct BanEntry {
+    uuid: Option<Uuid>,
+    name: Option<String>,
+    ip: Option<String>,
+    #[serde(flatten)]
+    ban: Ban,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Ban {
+    pub reason: String,
+    #[serde(default = "Ban::default_issuer")]
+    pub issuer: String,
+    /// Unix timestamp in seconds after which the ban no longer applies.
+    pub expires: Option<u64>,
+}
+
+impl Ban {
+    fn default_issuer() -> String {
+        "Lure".to_string()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|expires| expires <= unix_now())
+    }
+
+    /// Human readable time until the ban expires.
+    pub fn remaining(&self) -> String {
+        let expires = match self.expires {
+            Some(expires) => expires,
+            None => return "never".to_string(),
+        };
+
+        let secs = expires.saturating_sub(unix_now());
+        let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+
+        match (days, hours, minutes) {
+            (0, 0, 0) => format!("{secs}s"),
+            (0, 0, _) => format!("{minutes}m"),
+            (0, _, _) => format!("{hours}h {minutes}m"),
+            _ => format!("{days}d {hours}h"),
+        }
+    }
+
+    /// Fills the `{reason}`, `{issuer}` and `{expires}` placeholders of a
+    /// disconnect message.
+    pub fn format_message(&self, message: &str) -> String {
+        message
+            .replace("{reason}", &self.reason)
+            .replace("{issuer}", &self.issuer)
+            .replace("{expires}", &self.remaining())
+    }
+}
+
+/// Network-wide bans, reloaded whenever the ban file changes.
+#[derive(Debug)]
+pub struct BanList {
+    path: PathBuf,
+    inner: RwLock<BanEntries>,
+}
+
+#[derive(Debug, Default)]
+struct BanEntries {
+    modified: Option<SystemTime>,
+    uuids: HashMap<Uuid, Ban>,
+    /// Keyed by lowercase username.
+    names: HashMap<String, Ban>,
+    ips: Vec<(IpNet, Ban)>,
+}
+
+impl BanList {
+    pub fn new(path: &str) -> Self {
+        let bans = Self {
+            path: PathBuf::from(path),
+            inner: RwLock::new(BanEntries::default()),
+        };
+
+        if !bans.path.exists() {
+            println!(
+                "Ban file {} does not exist, creating an empty one.",
+                bans.path.display()
+            );
+            if let Err(e) = fs::write(&bans.path, "[]\n") {
+                eprintln!("Failed to create ban file {}: {e}", bans.path.display());
+            }
+        }
+
+        bans.refresh();
+        bans
+    }
+
+    /// Reloads the bans if the file was modified since the last check. The
+    /// previous bans are kept if the new file can't be parsed.
+    pub fn refresh(&self) {
+        let modified = file_modified(&self.path);
+        if self.inner.read().unwrap().modified == modified {
+            return;
+        }
+
+        let entries: Vec<BanEntry> = match fs::read_to_string(&self.path)
+            .map_err(anyhow::Error::from)
+            .and_then(|raw| Ok(serde_json::from_str(&raw)?))
+        {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Failed to load bans {}: {e}", self.path.display());
+                self.inner.write().unwrap().modified = modified;
+                return;
+            }
+        };
+
+        let mut loaded = BanEntries {
+            modified,
+            ..Default::default()
+        };
+        let count = entries.len();
+
+        for entry in entries {
+            if let Some(uuid) = entry.uuid {
+                loaded.uuids.insert(uuid, entry.ban);
+            } else if let Some(name) = entry.name {
+                loaded.names.insert(name.to_lowercase(), entry.ban);
+            } else if let Some(ip) = entry.ip {
+                match parse_ip_net(&ip) {
+                    Some(net) => loaded.ips.push((net, entry.ban)),
+                    None => eprintln!("Ignoring ban for invalid IP or CIDR range '{ip}'."),
+                }
+            } else {
+                eprintln!("Ignoring ban without uuid, name or ip: {:?}", entry.ban);
+            }
+        }
+
+        *self.inner.write().unwrap() = loaded;
+        println!("Loaded {count} bans from {}.", self.path.display());
+    }
+
+    /// Active ban covering the given address, if any.
+    pub fn find_ip(&self, ip: IpAddr) -> Option<Ban> {
+        let lck = self.inner.read().unwrap();
+        lck.ips
+            .iter()
+            .find(|(net, ban)| net.contains(&ip) && !ban.is_expired())
+            .map(|(_, ban)| ban.to_owned())
+    }
+
+    /// Active ban on the given account, if any.
+    pub fn find_account(&self, uuid: Uuid, username: &str) -> Option<Ban> {
+        let lck = self.inner.read().unwrap();
+        let name = username.to_lowercase();
+
+        let active = |ban: &&Ban| !ban.is_expired();
+        lck.uuids
+            .get(&uuid)
+            .filter(active)
+            .or_else(|| lck.names.get(&name).filter(active))
+            .cloned()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
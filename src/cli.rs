@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use tracing::Level;
+
+use crate::config::ConfigSource;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Minecraft proxy")]
+pub struct Cli {
+    /// Config file to use.
+    #[arg(short, long, global = true, default_value = "settings.toml")]
+    pub config: PathBuf,
+
+    /// Address to listen on, overriding the bind of the first listener.
+    #[arg(long, global = true)]
+    pub bind: Option<String>,
+
+    /// Most verbose log level shown: error, warn, info, debug or trace.
+    #[arg(long, global = true, default_value_t = Level::INFO)]
+    pub log_level: Level,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the proxy. This is the default.
+    Run,
+    /// Validate the config and exit, non-zero if there are problems.
+    Check,
+    /// Write a commented default config.
+    Init {
+        /// Overwrite the config if it already exists.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add missing keys with their default values to the config, keeping its
+    /// comments and formatting.
+    Fill,
+}
+
+impl Cli {
+    pub fn config_source(&self) -> anyhow::Result<ConfigSource> {
+        let path = self
+            .config
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("config path is not valid UTF-8"))?;
+
+        Ok(ConfigSource {
+            path: path.to_owned(),
+            bind: self.bind.to_owned(),
+        })
+    }
+}
@@ -1,14 +1,37 @@
-use std::{fs, collections::HashMap};
-use std::fs::File;
-use std::io::prelude::*;
+use std::{env, fs, collections::HashMap};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
-use serde::{Deserialize, Serialize};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use toml_edit::{Document, Table, TableLike};
+use tracing::info;
+use valence_protocol::{MAX_PACKET_SIZE, MINECRAFT_VERSION, PROTOCOL_VERSION};
+
+use crate::utils::toml_key;
 
 // Listener
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ListenerConfig {
     pub bind: String,
     pub max_connections: usize,
+    /// Longest hostname accepted in the handshake, in bytes, including any
+    /// Forge markers.
+    pub max_hostname_length: usize,
+    /// Addresses and CIDR ranges allowed to connect, anyone if empty.
+    pub allow: Vec<String>,
+    /// Addresses and CIDR ranges refused on this listener.
+    pub deny: Vec<String>,
+    /// Whether connections start with a PROXY protocol header carrying the
+    /// real client address. Only enable this behind a trusted load balancer,
+    /// restricted with `allow`.
+    pub proxy_protocol: bool,
+    /// Hosts reachable through this listener, as in `hosts`. All if empty.
+    pub hosts: Vec<String>,
+    /// Servers reachable through this listener. All if empty.
+    pub servers: Vec<String>,
 }
 
 impl Default for ListenerConfig {
@@ -16,17 +39,279 @@ impl Default for ListenerConfig {
         Self {
             bind: "127.0.0.1:25577".to_string(),
             max_connections: 8196,
+            max_hostname_length: 255,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            proxy_protocol: false,
+            hosts: Vec::new(),
+            servers: Vec::new(),
+        }
+    }
+}
+
+/// Reads either a single `[listener]` table or a `[[listener]]` list.
+fn deserialize_listeners<'de, D>(deserializer: D) -> Result<Vec<ListenerConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct ListenersVisitor;
+
+    impl<'de> Visitor<'de> for ListenersVisitor {
+        type Value = Vec<ListenerConfig>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a listener table or a list of them")
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Self::Value, M::Error> {
+            let listener = ListenerConfig::deserialize(de::value::MapAccessDeserializer::new(map))?;
+            Ok(vec![listener])
+        }
+
+        fn visit_seq<S: de::SeqAccess<'de>>(self, seq: S) -> Result<Self::Value, S::Error> {
+            Vec::deserialize(de::value::SeqAccessDeserializer::new(seq))
+        }
+    }
+
+    deserializer.deserialize_any(ListenersVisitor)
+}
+
+// Timeouts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    pub handshake_ms: u64,
+    pub status_ms: u64,
+    /// Whole login, including waiting for the encryption response and the
+    /// session server.
+    pub login_ms: u64,
+    /// Longest time without packets from either side while playing.
+    pub play_idle_ms: u64,
+    /// Time to connect to each address of a backend server before trying
+    /// the next one.
+    pub connect_ms: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            handshake_ms: 5_000,
+            status_ms: 5_000,
+            login_ms: 30_000,
+            play_idle_ms: 30_000,
+            connect_ms: 5_000,
+        }
+    }
+}
+
+// Packet limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PacketLimits {
+    /// Largest frame read from the socket, in bytes.
+    pub max_frame_size: usize,
+    /// Largest packet after decompression, in bytes.
+    pub max_decompressed_size: usize,
+}
+
+impl Default for PacketLimits {
+    fn default() -> Self {
+        Self {
+            max_frame_size: MAX_PACKET_SIZE as usize,
+            max_decompressed_size: MAX_PACKET_SIZE as usize,
+        }
+    }
+}
+
+/// Limits for packets sent by players in each connection state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PacketLimitsConfig {
+    pub handshake: PacketLimits,
+    pub status: PacketLimits,
+    pub login: PacketLimits,
+    pub play: PacketLimits,
+}
+
+impl Default for PacketLimitsConfig {
+    fn default() -> Self {
+        Self {
+            handshake: PacketLimits {
+                max_frame_size: 1024,
+                max_decompressed_size: 1024,
+            },
+            status: PacketLimits {
+                max_frame_size: 64,
+                max_decompressed_size: 64,
+            },
+            login: PacketLimits {
+                max_frame_size: 4096,
+                max_decompressed_size: 4096,
+            },
+            play: PacketLimits::default(),
+        }
+    }
+}
+
+// Rate limit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateBudget {
+    pub per_second: u32,
+    /// Most that may be spent at once after being idle, at least
+    /// `per_second`.
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub message: String,
+    pub packets: RateBudget,
+    pub bytes: RateBudget,
+    /// Packet budgets for specific packets by name, spent on top of
+    /// `packets`.
+    pub overrides: HashMap<String, RateBudget>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        let chat = RateBudget {
+            per_second: 5,
+            burst: 15,
+        };
+
+        Self {
+            enabled: true,
+            message: "§cYou are sending too many packets!".to_string(),
+            packets: RateBudget {
+                per_second: 300,
+                burst: 1000,
+            },
+            bytes: RateBudget {
+                per_second: 65_536,
+                burst: 1_048_576,
+            },
+            overrides: HashMap::from([
+                ("chat_message".to_string(), chat.to_owned()),
+                ("chat_command".to_string(), chat),
+            ]),
+        }
+    }
+}
+
+// Throttle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThrottleConfig {
+    pub max_connections_per_ip: u32,
+    pub window_ms: u64,
+    pub connections_per_window: u32,
+    pub status_per_window: u32,
+    pub logins_per_window: u32,
+    pub message: String,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_connections_per_ip: 10,
+            window_ms: 10_000,
+            connections_per_window: 30,
+            status_per_window: 15,
+            logins_per_window: 5,
+            message: "§cYou are connecting too fast, please wait a moment.".to_string(),
+        }
+    }
+}
+
+// Anti-bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AntiBotMode {
+    Off,
+    /// Verify only while the join rate is over the threshold.
+    Auto,
+    Always,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AntiBotConfig {
+    pub mode: AntiBotMode,
+    pub join_rate_threshold: u32,
+    pub active_secs: u64,
+    pub verified_secs: u64,
+    pub reconnect_min_delay_ms: u64,
+    pub reconnect_timeout_secs: u64,
+    pub message: String,
+}
+
+impl Default for AntiBotConfig {
+    fn default() -> Self {
+        Self {
+            mode: AntiBotMode::Auto,
+            join_rate_threshold: 20,
+            active_secs: 300,
+            verified_secs: 86400,
+            reconnect_min_delay_ms: 1000,
+            reconnect_timeout_secs: 60,
+            message: "§eChecking your connection, please reconnect to join.".to_string(),
+        }
+    }
+}
+
+// Usernames
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsernameConfig {
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Characters a username may contain, any character if empty.
+    pub allowed_characters: String,
+    /// Regex usernames have to match, unchecked if empty.
+    pub pattern: String,
+    /// Names nobody may join with, case-insensitive.
+    pub blocked: Vec<String>,
+    /// Reject names that only differ in case from an online player's.
+    pub deny_case_collisions: bool,
+    /// Disconnect message for names outside the length range, with `{min}`
+    /// and `{max}` placeholders.
+    pub length_message: String,
+    pub characters_message: String,
+    pub pattern_message: String,
+    pub blocked_message: String,
+    pub collision_message: String,
+}
+
+impl Default for UsernameConfig {
+    fn default() -> Self {
+        Self {
+            min_length: 3,
+            max_length: 16,
+            allowed_characters:
+                "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_".to_string(),
+            pattern: String::new(),
+            blocked: Vec::new(),
+            deny_case_collisions: true,
+            length_message: "§cYour username must be between {min} and {max} characters long."
+                .to_string(),
+            characters_message: "§cYour username contains characters that are not allowed."
+                .to_string(),
+            pattern_message: "§cYour username is not allowed on this server.".to_string(),
+            blocked_message: "§cThis username is reserved.".to_string(),
+            collision_message: "§cA player with the same name is already online.".to_string(),
         }
     }
 }
 
 // Proxy
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ProxyConfig {
     pub compression_threshold: u32,
     pub max_players: i32,
     pub online_mode: bool,
-    pub player_forward_mode: String,
+    pub player_forward_mode: ForwardingMode,
     pub player_limit: i32,
     pub prevent_proxy_connections: bool,
     pub motd: String,
@@ -39,7 +324,7 @@ impl Default for ProxyConfig {
             compression_threshold: 256,
             max_players: 4000,
             online_mode: true,
-            player_forward_mode: "none".to_string(),
+            player_forward_mode: ForwardingMode::None,
             player_limit: -1,
             prevent_proxy_connections: false,
             motd: "§dAnother Lure proxy".to_string(),
@@ -48,16 +333,342 @@ impl Default for ProxyConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Protocol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolRange {
+    pub min_version: i32,
+    pub max_version: i32,
+    pub version_name: String,
+}
+
+impl ProtocolRange {
+    pub fn contains(&self, protocol_version: i32) -> bool {
+        (self.min_version..=self.max_version).contains(&protocol_version)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProtocolConfig {
+    pub min_version: i32,
+    pub max_version: i32,
+    pub version_name: String,
+    pub outdated_client_message: String,
+    pub outdated_server_message: String,
+    #[serde(deserialize_with = "deserialize_host_keys")]
+    pub hosts: HashMap<String, ProtocolRange>,
+}
+
+impl Default for ProtocolConfig {
+    fn default() -> Self {
+        Self {
+            min_version: PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            version_name: MINECRAFT_VERSION.to_string(),
+            outdated_client_message: "§cOutdated client! Please use {version}".to_string(),
+            outdated_server_message: "§cOutdated server! I'm still on {version}".to_string(),
+            hosts: HashMap::new(),
+        }
+    }
+}
+
+impl ProtocolConfig {
+    /// Supported range for a hostname, falling back to the global range.
+    pub fn range(&self, hostname: &str) -> ProtocolRange {
+        match self.hosts.get(hostname) {
+            Some(range) => range.to_owned(),
+            None => ProtocolRange {
+                min_version: self.min_version,
+                max_version: self.max_version,
+                version_name: self.version_name.to_owned(),
+            },
+        }
+    }
+}
+
+/// Reads a table keyed by hostname, lowercasing the keys to match the
+/// normalised handshake hostname.
+fn deserialize_host_keys<'de, D, V>(deserializer: D) -> Result<HashMap<String, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    let hosts = HashMap::<String, V>::deserialize(deserializer)?;
+    Ok(hosts
+        .into_iter()
+        .map(|(hostname, value)| (hostname.to_ascii_lowercase(), value))
+        .collect())
+}
+
+// Whitelist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WhitelistConfig {
+    pub enabled: bool,
+    pub file: String,
+    pub message: String,
+    /// Per-host overrides of `enabled`.
+    #[serde(deserialize_with = "deserialize_host_keys")]
+    pub hosts: HashMap<String, bool>,
+}
+
+impl Default for WhitelistConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file: "whitelist.json".to_string(),
+            message: "§cYou are not whitelisted on this server!".to_string(),
+            hosts: HashMap::new(),
+        }
+    }
+}
+
+impl WhitelistConfig {
+    pub fn is_enabled(&self, hostname: &str) -> bool {
+        self.hosts.get(hostname).copied().unwrap_or(self.enabled)
+    }
+
+    /// Whether any host has the whitelist enabled.
+    pub fn is_used(&self) -> bool {
+        self.enabled || self.hosts.values().any(|enabled| *enabled)
+    }
+}
+
+// Bans
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BansConfig {
+    pub file: String,
+    /// Disconnect message, with `{reason}`, `{issuer}` and `{expires}`
+    /// placeholders.
+    pub message: String,
+}
+
+impl Default for BansConfig {
+    fn default() -> Self {
+        Self {
+            file: "bans.json".to_string(),
+            message: "§cYou are banned from this network.\n\n§7Reason: §f{reason}\n§7Expires in: §f{expires}".to_string(),
+        }
+    }
+}
+
+// Deny list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DenyListConfig {
+    pub file: String,
+}
+
+impl Default for DenyListConfig {
+    fn default() -> Self {
+        Self {
+            file: "deny-list.txt".to_string(),
+        }
+    }
+}
+
+// Servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardingMode {
+    /// Backends see the proxy's address and offline mode UUIDs.
+    None,
+    /// Player address, UUID and properties in the handshake hostname.
+    Bungeecord,
+    /// Like `Bungeecord`, with the server's secret as a `bungeeguard-token`
+    /// property.
+    Bungeeguard,
+}
+
+/// Port of servers given without one and without SRV records.
+pub const DEFAULT_PORT: u16 = 25565;
+
+/// Where a backend server listens.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BackendAddress {
+    /// An IP address and port, used as is.
+    Socket(SocketAddr),
+    /// A hostname and port, resolved through its A and AAAA records.
+    Host(String, u16),
+    /// A hostname without a port, resolved like the Minecraft client does:
+    /// through its `_minecraft._tcp` SRV records, or its A and AAAA records
+    /// on port 25565 if it has none.
+    Srv(String),
+}
+
+impl BackendAddress {
+    fn is_hostname(value: &str) -> bool {
+        !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+    }
+}
+
+impl From<SocketAddr> for BackendAddress {
+    fn from(address: SocketAddr) -> Self {
+        Self::Socket(address)
+    }
+}
+
+impl std::str::FromStr for BackendAddress {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Ok(address) = value.parse() {
+            return Ok(Self::Socket(address));
+        }
+        if let Ok(ip) = value.parse() {
+            return Ok(Self::Socket(SocketAddr::new(ip, DEFAULT_PORT)));
+        }
+
+        let invalid = || format!("invalid server address '{value}', expected host:port or host");
+        match value.rsplit_once(':') {
+            Some((host, port)) if Self::is_hostname(host) => {
+                Ok(Self::Host(host.to_owned(), port.parse().map_err(|_| invalid())?))
+            }
+            None if Self::is_hostname(value) => Ok(Self::Srv(value.to_owned())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for BackendAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Socket(address) => write!(f, "{address}"),
+            Self::Host(host, port) => write!(f, "{host}:{port}"),
+            Self::Srv(host) => f.write_str(host),
+        }
+    }
+}
+
+impl Serialize for BackendAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for BackendAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Backend server. Can also be written as just its address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self", deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Parsed with [`ServerConfig::backend_address`], once validation has
+    /// reported every bad one.
+    pub address: String,
+    /// Overrides `proxy.player_forward_mode`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarding: Option<ForwardingMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Only whitelisted players may join through hosts sending them here.
+    #[serde(default)]
+    pub restricted: bool,
+    /// Overrides `proxy.motd` for hosts sending players here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motd: Option<String>,
+    /// Overrides `proxy.compression_threshold` for players sent here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_threshold: Option<u32>,
+}
+
+impl ServerConfig {
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            forwarding: None,
+            secret: None,
+            restricted: false,
+            motd: None,
+            compression_threshold: None,
+        }
+    }
+
+    pub fn backend_address(&self) -> anyhow::Result<BackendAddress> {
+        self.address.parse().map_err(anyhow::Error::msg)
+    }
+}
+
+impl Serialize for ServerConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ServerConfig::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ServerConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ServerVisitor;
+
+        impl<'de> Visitor<'de> for ServerVisitor {
+            type Value = ServerConfig;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a server address or table")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<ServerConfig, E> {
+                Ok(ServerConfig::new(value))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<ServerConfig, M::Error> {
+                ServerConfig::deserialize(de::value::MapAccessDeserializer::new(map))
+            }
+        }
+
+        deserializer.deserialize_any(ServerVisitor)
+    }
+}
+
+/// Commented config written by `lure init` and on first start.
+pub const DEFAULT_SETTINGS: &str = include_str!("../assets/default_settings.toml");
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LureConfig {
+    #[serde(default = "LureConfig::default_listeners", deserialize_with = "deserialize_listeners")]
+    pub listener: Vec<ListenerConfig>,
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+    #[serde(default)]
+    pub limits: PacketLimitsConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub throttle: ThrottleConfig,
     #[serde(default)]
-    pub listener: ListenerConfig,
+    pub antibot: AntiBotConfig,
+    #[serde(default)]
+    pub usernames: UsernameConfig,
     #[serde(default)]
     pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub protocol: ProtocolConfig,
+    #[serde(default)]
+    pub whitelist: WhitelistConfig,
+    #[serde(default)]
+    pub bans: BansConfig,
+    #[serde(default)]
+    pub deny_list: DenyListConfig,
     #[serde(default = "LureConfig::default_hosts")]
     pub hosts: HashMap<String, String>,
     #[serde(default = "LureConfig::default_servers")]
-    pub servers: HashMap<String, String>,
+    pub servers: HashMap<String, ServerConfig>,
+    /// Patterns of files whose `servers` and `hosts` are merged into these,
+    /// relative to the config file.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// File each included server and host came from, by TOML path such as
+    /// `servers.lobby`.
+    #[serde(skip)]
+    pub sources: HashMap<String, String>,
     #[serde(flatten)]
     pub other_fields: HashMap<String, toml::value::Value>,
 }
@@ -65,48 +676,755 @@ pub struct LureConfig {
 impl Default for LureConfig {
     fn default() -> Self {
         Self {
-            listener: Default::default(),
+            listener: Self::default_listeners(),
+            timeouts: Default::default(),
+            limits: Default::default(),
+            rate_limit: Default::default(),
+            throttle: Default::default(),
+            antibot: Default::default(),
+            usernames: Default::default(),
             proxy: Default::default(),
+            protocol: Default::default(),
+            whitelist: Default::default(),
+            bans: Default::default(),
+            deny_list: Default::default(),
             hosts: Self::default_hosts(),
             servers: Self::default_servers(),
+            include: Default::default(),
+            sources: Default::default(),
             other_fields: Default::default()
         }
     }
 }
 
 impl LureConfig {
+    fn default_listeners() -> Vec<ListenerConfig> {
+        vec![ListenerConfig::default()]
+    }
+
     fn default_hosts() -> HashMap<String, String> {
         let mut hosts = HashMap::new();
         hosts.insert("*".to_string(), "lobby".into());
         hosts
     }
 
-    fn default_servers() -> HashMap<String, String> {
+    fn default_servers() -> HashMap<String, ServerConfig> {
         let mut servers = HashMap::new();
-        servers.insert("lobby".to_string(), "127.0.0.1:25565".into());
+        servers.insert("lobby".to_string(), ServerConfig::new("127.0.0.1:25565"));
         servers
     }
 
-    pub fn load (path: &str) -> anyhow::Result<Self, LureConfigLoadError> {
-        let raw = fs::read_to_string(path).map_err(|err| LureConfigLoadError::Io(err))?;
-        let config: Self = toml::from_str(&raw).map_err(|err| LureConfigLoadError::Parse(err))?;
+    fn from_value(value: toml::Value) -> anyhow::Result<Self, LureConfigLoadError> {
+        value.try_into().map_err(LureConfigLoadError::Parse)
+    }
+
+    /// Adds the keys missing from the config file with their default values,
+    /// leaving everything already in the file, comments and formatting
+    /// included, as it is. Tables keyed by names of your choosing, such as
+    /// `servers`, are never filled in. Returns the keys that were added.
+    pub fn fill_missing(path: &str) -> anyhow::Result<Vec<String>> {
+        let mut document: Document = fs::read_to_string(path)?.parse()?;
+        let defaults: Document = toml::to_string(&Self::default())?.parse()?;
+
+        let mut added = Vec::new();
+        let mut position = last_position(document.as_table()) + 1;
+        fill_table(
+            document.as_table_mut(),
+            defaults.as_table(),
+            "",
+            &mut added,
+            &mut position,
+        );
+
+        if !added.is_empty() {
+            fs::write(path, document.to_string())?;
+        }
+        Ok(added)
+    }
+}
+
+/// Tables whose keys are chosen by the user rather than being settings.
+const USER_KEYED_TABLES: [&str; 5] = [
+    "hosts",
+    "servers",
+    "rate_limit.overrides",
+    "whitelist.hosts",
+    "protocol.hosts",
+];
+
+/// Keys left out when missing, as their absence already means the default.
+const OPTIONAL_KEYS: [&str; 1] = ["include"];
+
+/// Adds the keys of `defaults` missing from `table`. Added tables are
+/// numbered from `position` on, so they're written after the existing ones.
+fn fill_table(
+    table: &mut dyn TableLike,
+    defaults: &dyn TableLike,
+    prefix: &str,
+    added: &mut Vec<String>,
+    position: &mut usize,
+) {
+    for (key, default) in defaults.iter() {
+        let path = match prefix {
+            "" => key.to_owned(),
+            prefix => format!("{prefix}.{key}"),
+        };
 
-        for field in &config.other_fields {
-            println!("Unknown configuration '{}' with value {:?}", field.0, field.1);
+        match table.get_mut(key) {
+            Some(item) => {
+                if USER_KEYED_TABLES.contains(&path.as_str()) {
+                    continue;
+                }
+                // Lists of tables such as `listener` take their defaults
+                // from the first entry of the template.
+                let defaults = match default.as_array_of_tables() {
+                    Some(defaults) => defaults.get(0).map(|table| table as &dyn TableLike),
+                    None => default.as_table_like(),
+                };
+                let Some(defaults) = defaults else {
+                    continue;
+                };
+
+                if let Some(tables) = item.as_array_of_tables_mut() {
+                    for (i, table) in tables.iter_mut().enumerate() {
+                        fill_table(table, defaults, &format!("{path}[{i}]"), added, position);
+                    }
+                } else if let Some(table) = item.as_table_like_mut() {
+                    fill_table(table, defaults, &path, added, position);
+                }
+            }
+            None if OPTIONAL_KEYS.contains(&path.as_str()) => {}
+            None => {
+                let mut item = default.to_owned();
+                if let Some(table) = item.as_table_mut() {
+                    set_positions(table, position);
+                }
+                if let Some(tables) = item.as_array_of_tables_mut() {
+                    tables
+                        .iter_mut()
+                        .for_each(|table| set_positions(table, position));
+                }
+                table.insert(key, item);
+                added.push(path);
+            }
         }
+    }
+}
+
+fn set_positions(table: &mut Table, position: &mut usize) {
+    table.set_position(*position);
+    *position += 1;
+
+    for (_, item) in table.iter_mut() {
+        if let Some(table) = item.as_table_mut() {
+            set_positions(table, position);
+        }
+    }
+}
 
+fn last_position(table: &Table) -> usize {
+    table
+        .iter()
+        .flat_map(|(_, item)| {
+            let tables = item.as_array_of_tables().into_iter().flatten();
+            item.as_table().into_iter().chain(tables)
+        })
+        .map(last_position)
+        .chain(table.position())
+        .max()
+        .unwrap_or(0)
+}
+
+fn read_toml(path: &str) -> anyhow::Result<toml::Value, LureConfigLoadError> {
+    let raw = fs::read_to_string(path).map_err(LureConfigLoadError::Io)?;
+    toml::from_str(&raw).map_err(LureConfigLoadError::Parse)
+}
+
+/// Prefix of environment variables overriding config keys.
+const ENV_PREFIX: &str = "LURE_";
+
+/// Config file and the overrides applied on top of it every time it is
+/// loaded: environment variables first, then the command line.
+#[derive(Clone, Debug)]
+pub struct ConfigSource {
+    pub path: String,
+    pub bind: Option<String>,
+}
+
+impl ConfigSource {
+    pub fn load(&self) -> anyhow::Result<LureConfig, LureConfigLoadError> {
+        let mut value = read_toml(&self.path)?;
+        let sources = merge_includes(&mut value, &self.path)?;
+        for key in apply_env_overrides(&mut value, env::vars())? {
+            info!("Config key {key} set from the environment.");
+        }
+
+        let mut config = LureConfig::from_value(value)?;
+        config.sources = sources;
+        if let Some(bind) = &self.bind {
+            if let Some(listener) = config.listener.first_mut() {
+                listener.bind = bind.to_owned();
+            }
+        }
         Ok(config)
     }
 
-    pub fn save (&self, path: &str) -> anyhow::Result<()>{
-        let config_str = toml::to_string(&self)?;
-        let mut file = File::create(path)?;
-        file.write_all(config_str.as_bytes())?;
-        Ok(())
+    /// Config file and the files it currently includes.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![PathBuf::from(&self.path)];
+        if let Ok(included) =
+            read_toml(&self.path).and_then(|value| included_files(&value, &self.path))
+        {
+            files.extend(included);
+        }
+        files
+    }
+}
+
+/// Top-level tables included files may define.
+const INCLUDED_TABLES: [&str; 2] = ["servers", "hosts"];
+
+/// Files matching the `include` patterns, in order and each only once.
+/// Patterns are relative to the directory of the config file.
+fn included_files(
+    config: &toml::Value,
+    path: &str,
+) -> anyhow::Result<Vec<PathBuf>, LureConfigLoadError> {
+    let invalid =
+        || LureConfigLoadError::Include("include must be a list of file patterns".to_string());
+    let patterns = match config.get("include") {
+        Some(patterns) => patterns.as_array().ok_or_else(invalid)?,
+        None => return Ok(Vec::new()),
+    };
+
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let pattern = dir.join(pattern.as_str().ok_or_else(invalid)?);
+        let pattern = pattern.to_string_lossy();
+        let paths = glob::glob(&pattern).map_err(|e| {
+            LureConfigLoadError::Include(format!("invalid include pattern '{pattern}': {e}"))
+        })?;
+
+        let mut matched = Vec::new();
+        for path in paths {
+            let path = path.map_err(|e| {
+                LureConfigLoadError::Include(format!("{}: {}", e.path().display(), e.error()))
+            })?;
+            if !files.contains(&path) {
+                matched.push(path);
+            }
+        }
+        matched.sort();
+        files.extend(matched);
+    }
+
+    Ok(files)
+}
+
+/// Merges the servers and hosts of included files into the config. Keys
+/// may only be defined once across all files. Returns the file each merged
+/// key came from.
+fn merge_includes(
+    config: &mut toml::Value,
+    path: &str,
+) -> anyhow::Result<HashMap<String, String>, LureConfigLoadError> {
+    let mut sources: HashMap<String, String> = HashMap::new();
+
+    for file in included_files(config, path)? {
+        let name = file.display().to_string();
+        let error = |message: String| LureConfigLoadError::Include(format!("{name}: {message}"));
+        let included = match read_toml(&name).map_err(|e| error(e.to_string()))? {
+            toml::Value::Table(included) => included,
+            _ => continue,
+        };
+
+        for (table, entries) in included {
+            if !INCLUDED_TABLES.contains(&table.as_str()) {
+                return Err(error(format!(
+                    "only servers and hosts can be included, found '{table}'"
+                )));
+            }
+            let entries = match entries {
+                toml::Value::Table(entries) => entries,
+                _ => return Err(error(format!("{table} must be a table"))),
+            };
+
+            let target = env_entry(config, &table, None)
+                .ok()
+                .and_then(toml::Value::as_table_mut)
+                .ok_or_else(|| {
+                    LureConfigLoadError::Include(format!("{table} in {path} must be a table"))
+                })?;
+            for (key, entry) in entries {
+                let key_path = format!("{table}.{}", toml_key(&key));
+                if target.contains_key(&key) {
+                    let other = sources.get(&key_path).map(String::as_str).unwrap_or(path);
+                    return Err(error(format!("{key_path} is already defined in {other}")));
+                }
+
+                // Check entries here, while the file they came from is known.
+                let checked = match table.as_str() {
+                    "servers" => entry.clone().try_into::<ServerConfig>().map(drop),
+                    _ => entry.clone().try_into::<String>().map(drop),
+                };
+                if let Err(e) = checked {
+                    return Err(error(format!("{key_path}: {e}")));
+                }
+
+                target.insert(key, entry);
+                sources.insert(key_path, name.to_owned());
+            }
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Sets config keys from `LURE_SECTION__KEY` variables, e.g.
+/// `LURE_PROXY__ONLINE_MODE=false`, `LURE_SERVERS__lobby=10.0.0.5:25565` or
+/// `LURE_LISTENER__0__BIND=0.0.0.0:25565`.
+/// All-uppercase segments are lowercased, others such as server names are
+/// kept as they are. Values of string keys are taken as they are, others
+/// are parsed as TOML, falling back to a plain string, and a `_FILE` suffix
+/// on the last segment reads the value from the file the variable points
+/// to. Returns the keys that were set.
+fn apply_env_overrides(
+    config: &mut toml::Value,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> anyhow::Result<Vec<String>, LureConfigLoadError> {
+    let mut keys = Vec::new();
+    let template = env_template();
+
+    for (name, raw) in vars {
+        let path = match name.strip_prefix(ENV_PREFIX) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        let mut segments: Vec<String> = path
+            .split("__")
+            .map(|segment| match segment.chars().any(|c| c.is_ascii_lowercase()) {
+                true => segment.to_owned(),
+                false => segment.to_ascii_lowercase(),
+            })
+            .collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(LureConfigLoadError::Env(format!("invalid config key in {name}")));
+        }
+
+        let last = segments.last_mut().unwrap();
+        let value = match last.strip_suffix("_file").filter(|key| !key.is_empty()) {
+            Some(key) => {
+                let contents = fs::read_to_string(&raw).map_err(|err| {
+                    LureConfigLoadError::Env(format!("failed to read {name} from {raw}: {err}"))
+                })?;
+                *last = key.to_owned();
+                toml::Value::String(contents.trim_end_matches(['\r', '\n']).to_owned())
+            }
+            None => match template_entry(&template, &segments) {
+                Some(toml::Value::String(_)) => toml::Value::String(raw),
+                _ => parse_env_value(&raw),
+            },
+        };
+
+        let mut entry = &mut *config;
+        let mut defaults = Some(&template);
+        for segment in &segments {
+            defaults = defaults.and_then(|defaults| default_entry(defaults, segment));
+            entry = env_entry(entry, segment, defaults)
+                .map_err(|err| LureConfigLoadError::Env(format!("{name}: {err}")))?;
+        }
+        *entry = value;
+
+        keys.push(segments.join("."));
     }
+
+    Ok(keys)
 }
 
+/// Child of a value by key, created from its defaults if it doesn't exist
+/// yet, so overrides of a section missing from the file keep the defaults of
+/// its other keys. Lists such as `listener` take an index, or apply other
+/// keys to their first entry.
+fn env_entry<'a>(
+    value: &'a mut toml::Value,
+    segment: &str,
+    defaults: Option<&toml::Value>,
+) -> anyhow::Result<&'a mut toml::Value, String> {
+    if value.is_array() {
+        let items = value.as_array_mut().unwrap();
+        let len = items.len();
+        return match segment.parse::<usize>() {
+            Ok(index) => items
+                .get_mut(index)
+                .ok_or_else(|| format!("index {index} is out of range, there are {len} entries")),
+            Err(_) => match items.first_mut() {
+                Some(first) if first.is_table() => env_entry(first, segment, defaults),
+                _ => Err(format!("expected an index instead of {segment}")),
+            },
+        };
+    }
+
+    if !value.is_table() {
+        *value = toml::Value::Table(Default::default());
+    }
+    Ok(value
+        .as_table_mut()
+        .unwrap()
+        .entry(segment.to_owned())
+        .or_insert_with(|| match defaults {
+            Some(defaults) => without_samples(defaults),
+            None => toml::Value::Table(Default::default()),
+        }))
+}
+
+/// Default value at the path of an override. Unlike [`template_entry`],
+/// entries keyed by the user only have defaults if the default config has
+/// them, such as `servers.lobby`.
+fn default_entry<'a>(defaults: &'a toml::Value, segment: &str) -> Option<&'a toml::Value> {
+    match defaults {
+        toml::Value::Array(items) => match segment.parse::<usize>() {
+            Ok(index) => items.get(index),
+            Err(_) => items.first()?.get(segment),
+        },
+        defaults => defaults.get(segment),
+    }
+}
+
+/// Copy of a template value without the sample entries under the empty key.
+fn without_samples(value: &toml::Value) -> toml::Value {
+    match value {
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .iter()
+                .filter(|(key, _)| !key.is_empty())
+                .map(|(key, value)| (key.to_owned(), without_samples(value)))
+                .collect(),
+        ),
+        toml::Value::Array(items) => {
+            toml::Value::Array(items.iter().map(without_samples).collect())
+        }
+        value => value.to_owned(),
+    }
+}
+
+/// Default config with a sample entry in every table keyed by the user,
+/// under the empty key, and every optional server field set, so it holds a
+/// value of the right type for every key.
+fn env_template() -> toml::Value {
+    let mut config = LureConfig::default();
+    let server = ServerConfig {
+        forwarding: Some(ForwardingMode::None),
+        secret: Some(String::new()),
+        motd: Some(String::new()),
+        compression_threshold: Some(0),
+        ..ServerConfig::new("")
+    };
+    config.servers.insert(String::new(), server);
+    config.hosts.insert(String::new(), String::new());
+    let budget = config.rate_limit.packets.to_owned();
+    config.rate_limit.overrides.insert(String::new(), budget);
+    let range = config.protocol.range("");
+    config.protocol.hosts.insert(String::new(), range);
+    config.whitelist.hosts.insert(String::new(), false);
+
+    toml::Value::try_from(config).expect("the default config serializes")
+}
+
+/// Value of the template at the path of an override, following
+/// [`env_entry`] into lists and user-keyed tables.
+fn template_entry<'a>(template: &'a toml::Value, segments: &[String]) -> Option<&'a toml::Value> {
+    let child = |value: &'a toml::Value, segment: &str| {
+        let table = value.as_table()?;
+        table.get("").or_else(|| table.get(segment))
+    };
+
+    segments.iter().try_fold(template, |value, segment| match value {
+        toml::Value::Array(items) => match segment.parse::<usize>() {
+            Ok(_) => items.first(),
+            Err(_) => child(items.first()?, segment),
+        },
+        value => child(value, segment),
+    })
+}
+
+/// Parses a variable as a TOML value, so `false` or `[1, 2]` keep their
+/// type, or takes it as a string if it isn't one. Keys holding strings skip
+/// this, so secrets such as `12345` stay strings.
+fn parse_env_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {raw}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_owned()))
+}
+
+#[derive(Debug)]
 pub enum LureConfigLoadError {
     Io(std::io::Error),
-    Parse(toml::de::Error)
-}
\ No newline at end of file
+    Parse(toml::de::Error),
+    Env(String),
+    Include(String),
+}
+
+impl fmt::Display for LureConfigLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LureConfigLoadError::Io(err) => write!(f, "failed to read config: {err}"),
+            LureConfigLoadError::Parse(err) => write!(f, "failed to parse config: {err}"),
+            LureConfigLoadError::Env(err) => write!(f, "invalid environment override: {err}"),
+            LureConfigLoadError::Include(err) => write!(f, "failed to include config: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LureConfigLoadError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Directory for a test's files, removed even if the test fails.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("lure-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn config_server_forms() {
+        let config: LureConfig = toml::from_str(
+            r#"
+            [servers]
+            lobby = "127.0.0.1:25565"
+            staff = { address = "10.0.0.2:25565", forwarding = "bungeeguard", secret = "s3cret", restricted = true }
+            "#,
+        )
+        .unwrap();
+
+        let lobby = ServerConfig::new("127.0.0.1:25565");
+        assert_eq!(config.servers["lobby"], lobby);
+        let staff = &config.servers["staff"];
+        assert_eq!(staff.forwarding, Some(ForwardingMode::Bungeeguard));
+        assert!(staff.restricted);
+
+        // Saved configs load back the same.
+        let saved = toml::to_string(&config).unwrap();
+        let reloaded: LureConfig = toml::from_str(&saved).unwrap();
+        assert_eq!(reloaded.servers, config.servers);
+
+        let addresses: LureConfig = toml::from_str(
+            r#"
+            [servers]
+            lobby = "lobby.internal:25566"
+            hub = "play.example.com"
+            staff = "10.0.0.2"
+            "#,
+        )
+        .unwrap();
+        let address = |name: &str| addresses.servers[name].backend_address().unwrap();
+        assert_eq!(address("lobby"), BackendAddress::Host("lobby.internal".to_string(), 25566));
+        assert_eq!(address("hub"), BackendAddress::Srv("play.example.com".to_string()));
+        assert_eq!(address("staff").to_string(), "10.0.0.2:25565");
+
+        assert!(ServerConfig::new("lobby:port").backend_address().is_err());
+        assert!(ServerConfig::new("lob by").backend_address().is_err());
+        assert!(toml::from_str::<LureConfig>(
+            "servers.lobby = { address = \"127.0.0.1:1\", typo = 1 }"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn config_forwarding_mode_strings() {
+        let mode: ForwardingMode = toml::Value::from("bungeecord").try_into().unwrap();
+        assert_eq!(mode, ForwardingMode::Bungeecord);
+        assert!(toml::Value::from("velocity").try_into::<ForwardingMode>().is_err());
+    }
+
+    #[test]
+    fn config_host_keys_ignore_case() {
+        let config: LureConfig = toml::from_str(
+            r#"
+            protocol.hosts."Event.Example.com" = { min_version = 1, max_version = 2, version_name = "old" }
+            whitelist.hosts."Event.Example.com" = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.protocol.range("event.example.com").version_name, "old");
+        assert!(config.whitelist.is_enabled("event.example.com"));
+    }
+
+    #[test]
+    fn config_default_settings_match_defaults() {
+        let config: LureConfig = toml::from_str(DEFAULT_SETTINGS).unwrap();
+        // New configs forward players BungeeCord style, while configs
+        // without the key keep forwarding off.
+        let mut defaults = LureConfig::default();
+        defaults.proxy.player_forward_mode = ForwardingMode::Bungeecord;
+        assert_eq!(config, defaults);
+        assert_eq!(crate::validation::validate(&config).errors(), 0);
+    }
+
+    #[test]
+    fn config_env_overrides() {
+        let dir = TempDir::new("config-env");
+        let secret_file = dir.0.join("secret");
+        fs::write(&secret_file, "hunter2\n").unwrap();
+
+        let mut value = toml::Value::try_from(LureConfig::default()).unwrap();
+        let vars = [
+            ("LURE_PROXY__ONLINE_MODE", "false"),
+            ("LURE_LISTENER__0__BIND", "0.0.0.0:25565"),
+            ("LURE_SERVERS__lobby", "10.0.0.5:25565"),
+            ("LURE_SERVERS__hub__ADDRESS", "10.0.0.6:25565"),
+            ("LURE_SERVERS__hub__SECRET_FILE", secret_file.to_str().unwrap()),
+            ("LURE_DENY_LIST__FILE", "denied.txt"),
+            ("LURE_TIMEOUTS__LOGIN_MS", "5000"),
+            ("LURE_PROXY__MOTD", "true"),
+            ("LURE_SERVERS__staff__ADDRESS", "10.0.0.7:25565"),
+            ("LURE_SERVERS__staff__SECRET", "12345"),
+            ("PATH", "/usr/bin"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let keys = apply_env_overrides(&mut value, vars).unwrap();
+        let config = LureConfig::from_value(value).unwrap();
+
+        assert_eq!(keys.len(), 10);
+        assert!(keys.contains(&"servers.hub.secret".to_string()));
+        assert!(!config.proxy.online_mode);
+        assert_eq!(config.listener[0].bind, "0.0.0.0:25565");
+        assert_eq!(config.servers["lobby"].address, "10.0.0.5:25565");
+        assert_eq!(config.servers["hub"].secret.as_deref(), Some("hunter2"));
+        assert_eq!(config.deny_list.file, "denied.txt");
+        assert_eq!(config.timeouts.login_ms, 5000);
+        assert_eq!(config.proxy.motd, "true");
+        assert_eq!(config.servers["staff"].secret.as_deref(), Some("12345"));
+    }
+
+    #[test]
+    fn config_env_overrides_lists() {
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            [[listener]]
+            bind = "0.0.0.0:25565"
+
+            [[listener]]
+            bind = "10.0.0.1:25565"
+            proxy_protocol = true
+            allow = ["10.0.0.2"]
+            "#,
+        )
+        .unwrap();
+        let var = |name: &str, value: &str| [(name.to_string(), value.to_string())];
+
+        apply_env_overrides(&mut value, var("LURE_LISTENER__BIND", "0.0.0.0:25566")).unwrap();
+        let config = LureConfig::from_value(value.clone()).unwrap();
+        assert_eq!(config.listener.len(), 2);
+        assert_eq!(config.listener[0].bind, "0.0.0.0:25566");
+        assert!(config.listener[1].proxy_protocol);
+        assert_eq!(config.listener[1].allow, ["10.0.0.2"]);
+
+        assert!(apply_env_overrides(&mut value, var("LURE_LISTENER__3__BIND", "x")).is_err());
+        assert!(apply_env_overrides(&mut value, var("LURE_LISTENER__1__ALLOW__X", "x")).is_err());
+    }
+
+    #[test]
+    fn config_env_overrides_missing_sections() {
+        let mut value: toml::Value = toml::from_str("servers.lobby = \"10.0.0.1:25565\"").unwrap();
+        let vars = [
+            ("LURE_PROXY__ONLINE_MODE", "false"),
+            ("LURE_LIMITS__PLAY__MAX_FRAME_SIZE", "4096"),
+            ("LURE_RATE_LIMIT__PACKETS__BURST", "2000"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        apply_env_overrides(&mut value, vars).unwrap();
+        let config = LureConfig::from_value(value).unwrap();
+        let defaults = LureConfig::default();
+        assert!(!config.proxy.online_mode);
+        assert_eq!(config.proxy.motd, defaults.proxy.motd);
+        assert_eq!(config.limits.play.max_frame_size, 4096);
+        assert_eq!(config.limits.login, defaults.limits.login);
+        assert_eq!(config.rate_limit.packets.burst, 2000);
+        assert_eq!(config.rate_limit.overrides, defaults.rate_limit.overrides);
+
+        let config: LureConfig = toml::from_str("[proxy]\nonline_mode = false").unwrap();
+        assert_eq!(config.proxy.player_forward_mode, ForwardingMode::None);
+    }
+
+    #[test]
+    fn config_fill_missing_keeps_document() {
+        let dir = TempDir::new("config-fill");
+        let path = dir.0.join("settings.toml");
+        let path = path.to_str().unwrap();
+        let raw = "# Staff proxy\n[proxy]\nonline_mode = false # testing\n\n[servers]\nhub = \"10.0.0.1:25565\"\n\n[[listener]]\nbind = \"0.0.0.0:25565\"\n";
+        fs::write(path, raw).unwrap();
+
+        let added = LureConfig::fill_missing(path).unwrap();
+        let filled = fs::read_to_string(path).unwrap();
+        let again = LureConfig::fill_missing(path).unwrap();
+
+        assert!(filled.starts_with("# Staff proxy\n[proxy]\nonline_mode = false # testing\n"));
+        assert!(filled.contains("\n[servers]\nhub = \"10.0.0.1:25565\"\n"));
+        assert!(added.contains(&"proxy.motd".to_string()));
+        assert!(added.contains(&"timeouts".to_string()));
+        assert!(added.contains(&"listener[0].proxy_protocol".to_string()));
+        assert!(again.is_empty());
+
+        let config: LureConfig = toml::from_str(&filled).unwrap();
+        assert!(!config.proxy.online_mode);
+        assert_eq!(config.servers.keys().collect::<Vec<_>>(), ["hub"]);
+        assert_eq!(config.listener[0].bind, "0.0.0.0:25565");
+    }
+
+    #[test]
+    fn config_includes() {
+        let dir = TempDir::new("config-includes");
+        let dir = &dir.0;
+        fs::create_dir_all(dir.join("servers.d")).unwrap();
+        let write = |name: &str, raw: &str| fs::write(dir.join(name), raw).unwrap();
+        write(
+            "settings.toml",
+            "include = [\"servers.d/*.toml\"]\n[hosts]\n\"*\" = \"lobby\"\n",
+        );
+        write("servers.d/a.toml", "[servers]\nlobby = \"10.0.0.1:25565\"\n");
+        write(
+            "servers.d/b.toml",
+            "[servers]\nhub = \"10.0.0.2:25565\"\n[hosts]\n\"hub.example.com\" = \"hub\"\n",
+        );
+        let source = ConfigSource {
+            path: dir.join("settings.toml").to_str().unwrap().to_owned(),
+            bind: None,
+        };
+
+        let config = source.load().unwrap();
+        let b = dir.join("servers.d/b.toml").display().to_string();
+        assert_eq!(config.servers.len(), 2);
+        assert_eq!(config.hosts["hub.example.com"], "hub");
+        assert_eq!(config.sources["hosts.\"hub.example.com\""], b);
+        assert_eq!(source.files().len(), 3);
+
+        write("servers.d/c.toml", "[servers]\nhub = \"10.0.0.3:25565\"\n");
+        let error = source.load().unwrap_err().to_string();
+        assert!(error.contains("c.toml: servers.hub is already defined in"));
+        assert!(error.ends_with(&b));
+
+        write("servers.d/c.toml", "[servers]\nstaff = { adress = \"10.0.0.3:25565\" }\n");
+        let error = format!("{:#}", source.load().unwrap_err());
+        assert!(error.contains("c.toml: servers.staff: "), "{error}");
+    }
+}
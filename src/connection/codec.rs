@@ -6,6 +6,8 @@ use tracing::debug;
 use valence_protocol::var_int::{VarInt, VarIntDecodeError};
 use valence_protocol::{Decode, DecodePacket, Encode, EncodePacket, Result, MAX_PACKET_SIZE};
 
+use crate::config::PacketLimits;
+
 type Cipher = cfb8::Cfb8<aes::Aes128>;
 
 #[derive(Default)]
@@ -263,6 +265,7 @@ pub struct PacketDecoder {
     pub decompress_buf: Vec<u8>,
     pub compression_enabled: bool,
     pub cipher: Option<Cipher>,
+    pub limits: PacketLimits,
 }
 
 impl PacketDecoder {
@@ -270,6 +273,22 @@ impl PacketDecoder {
         Self::default()
     }
 
+    /// Sets the frame and decompressed size limits for the connection's
+    /// current state.
+    pub fn set_limits(&mut self, limits: PacketLimits) {
+        self.limits = limits;
+    }
+
+    fn check_frame_len(&self, packet_len: i32) -> Result<()> {
+        ensure!(packet_len >= 0, "negative packet length {packet_len}");
+        ensure!(
+            packet_len as usize <= self.limits.max_frame_size,
+            "packet length of {packet_len} exceeds the limit of {} bytes",
+            self.limits.max_frame_size
+        );
+        Ok(())
+    }
+
     pub fn try_next_packet<'a, P>(&'a mut self) -> Result<Option<P>>
     where
         P: DecodePacket<'a>,
@@ -285,10 +304,7 @@ impl PacketDecoder {
             Err(VarIntDecodeError::TooLarge) => bail!("malformed packet length VarInt"),
         };
 
-        ensure!(
-            (0..=MAX_PACKET_SIZE).contains(&packet_len),
-            "packet length of {packet_len} is out of bounds"
-        );
+        self.check_frame_len(packet_len)?;
 
         if r.len() < packet_len as usize {
             return Ok(None);
@@ -304,9 +320,11 @@ impl PacketDecoder {
 
             let data_len = VarInt::decode(&mut r)?.0;
 
+            ensure!(data_len >= 0, "negative decompressed packet length {data_len}");
             ensure!(
-                (0..MAX_PACKET_SIZE).contains(&data_len),
-                "decompressed packet length of {data_len} is out of bounds"
+                data_len as usize <= self.limits.max_decompressed_size,
+                "decompressed packet length of {data_len} exceeds the limit of {} bytes",
+                self.limits.max_decompressed_size
             );
 
             if data_len != 0 {
@@ -317,6 +335,12 @@ impl PacketDecoder {
                 z.read_to_end(&mut self.decompress_buf)
                     .context("decompressing packet")?;
 
+                ensure!(
+                    self.decompress_buf.len() == data_len as usize,
+                    "decompressed packet is {} bytes, but claimed {data_len}",
+                    self.decompress_buf.len()
+                );
+
                 r = &self.decompress_buf;
                 P::decode_packet(&mut r)?
             } else {
@@ -401,16 +425,52 @@ impl PacketDecoder {
         }
     }
 
+    /// ID and frame size of the next complete packet, without consuming it.
+    pub fn peek_packet(&self) -> Result<Option<(i32, usize)>> {
+        let mut r = &self.buf[self.cursor..];
+
+        let packet_len = match VarInt::decode_partial(&mut r) {
+            Ok(len) => len,
+            Err(VarIntDecodeError::Incomplete) => return Ok(None),
+            Err(VarIntDecodeError::TooLarge) => bail!("malformed packet length VarInt"),
+        };
+
+        self.check_frame_len(packet_len)?;
+
+        if r.len() < packet_len as usize {
+            return Ok(None);
+        }
+
+        let frame_size = VarInt(packet_len).written_size() + packet_len as usize;
+        r = &r[..packet_len as usize];
+
+        let id = if self.compression_enabled && VarInt::decode(&mut r)?.0 != 0 {
+            use std::io::Read;
+
+            use anyhow::Context;
+            use flate2::bufread::ZlibDecoder;
+
+            // Only the packet ID prefix has to be inflated.
+            let mut prefix = Vec::with_capacity(VarInt::MAX_SIZE);
+            ZlibDecoder::new(r)
+                .take(VarInt::MAX_SIZE as u64)
+                .read_to_end(&mut prefix)
+                .context("decompressing packet ID")?;
+
+            VarInt::decode(&mut prefix.as_slice())?.0
+        } else {
+            VarInt::decode(&mut r)?.0
+        };
+
+        Ok(Some((id, frame_size)))
+    }
+
     pub fn has_next_packet(&self) -> Result<bool> {
         let mut r = &self.buf[self.cursor..];
 
         match VarInt::decode_partial(&mut r) {
             Ok(packet_len) => {
-                ensure!(
-                    (0..=MAX_PACKET_SIZE).contains(&packet_len),
-                    "packet length of {packet_len} is out of bounds"
-                );
-
+                self.check_frame_len(packet_len)?;
                 Ok(r.len() >= packet_len as usize)
             }
             Err(VarIntDecodeError::Incomplete) => Ok(false),
@@ -463,3 +523,28 @@ impl PacketDecoder {
         self.buf.reserve(additional);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codec_frame_limit() {
+        let limits = PacketLimits {
+            max_frame_size: 64,
+            max_decompressed_size: 64,
+        };
+
+        // Length prefix of 64 is accepted while waiting for the rest.
+        let mut dec = PacketDecoder::new();
+        dec.set_limits(limits);
+        dec.queue_slice(&[0x40]);
+        assert!(!dec.has_next_packet().unwrap());
+
+        // Length prefix of 300 is rejected before the frame is buffered.
+        let mut dec = PacketDecoder::new();
+        dec.set_limits(limits);
+        dec.queue_slice(&[0xac, 0x02]);
+        assert!(dec.has_next_packet().is_err());
+    }
+}
@@ -46,20 +46,22 @@ impl Connection {
     where
         P: DecodePacket<'a> + EncodePacket,
     {
-        while !self.dec.has_next_packet()? {
-            self.dec.reserve(READ_BUF_SIZE);
-            let mut buf = self.dec.take_capacity();
+        self.fill_packet().await?;
 
-            if self.read.read_buf(&mut buf).await? == 0 {
-                return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
-            }
+        Ok(self
+            .dec
+            .try_next_packet()?
+            .expect("decoder said it had another packet"))
+    }
 
-            self.dec.queue_bytes(buf);
-        }
+    /// Waits for the next packet and returns its ID and frame size, leaving it
+    /// in the decoder.
+    pub async fn peek_packet(&mut self) -> anyhow::Result<(i32, usize)> {
+        self.fill_packet().await?;
 
         Ok(self
             .dec
-            .try_next_packet()?
+            .peek_packet()?
             .expect("decoder said it had another packet"))
     }
 
@@ -77,8 +79,23 @@ impl Connection {
     where
         P: DecodePacket<'a> + EncodePacket,
     {
+        self.fill_packet().await?;
+
+        let pkt: P = self.dec.try_next_packet()?.expect("Packet was None");
+        self.enc.append_packet(&pkt)?;
+
+        let bytes = self.enc.take();
+        self.write.write_all(&bytes).await?;
+
+        self.buf.clear();
+
+        Ok(())
+    }
+
+    /// Reads from the socket until the decoder holds a complete packet.
+    async fn fill_packet(&mut self) -> anyhow::Result<()> {
         while !self.dec.has_next_packet()? {
-            self.dec.reserve(4096);
+            self.dec.reserve(READ_BUF_SIZE);
             let mut buf = self.dec.take_capacity();
 
             if self.read.read_buf(&mut buf).await? == 0 {
@@ -88,14 +105,6 @@ impl Connection {
             self.dec.queue_bytes(buf);
         }
 
-        let pkt: P = self.dec.try_next_packet()?.expect("Packet was None");
-        self.enc.append_packet(&pkt)?;
-
-        let bytes = self.enc.take();
-        self.write.write_all(&bytes).await?;
-
-        self.buf.clear();
-
         Ok(())
     }
 }
@@ -0,0 +1,134 @@
+//! Server list ping used by clients older than 1.7, which predates the
+//! handshake packet and is answered with a single kick packet.
+
+/// First byte sent by every legacy ping. Modern clients open with a VarInt
+/// packet length instead, which never starts with this value for a handshake.
+pub const LEGACY_PING_PACKET_ID: u8 = 0xFE;
+
+/// Legacy kick packet carrying the server list response.
+const LEGACY_KICK_PACKET_ID: u8 = 0xFF;
+
+/// Protocol number reported to legacy clients. It never matches a real
+/// release, so the client shows the version name instead of a ping bar.
+const LEGACY_PROTOCOL_VERSION: i32 = 127;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LegacyPingFormat {
+    /// Beta 1.8 to 1.3, a lone `0xFE`.
+    Beta,
+    /// 1.4 to 1.6, `0xFE 0x01` optionally followed by a `MC|PingHost` message.
+    V1_4,
+}
+
+impl LegacyPingFormat {
+    pub fn detect(request: &[u8]) -> Self {
+        match request {
+            [LEGACY_PING_PACKET_ID, 0x01, ..] => LegacyPingFormat::V1_4,
+            _ => LegacyPingFormat::Beta,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LegacyPingResponse {
+    pub version: String,
+    pub motd: String,
+    pub online: i32,
+    pub max: i32,
+}
+
+impl LegacyPingResponse {
+    /// Encodes the response as a kick packet in the given format.
+    pub fn encode(&self, format: LegacyPingFormat) -> Vec<u8> {
+        // Legacy clients can only show a single line.
+        let motd = self.motd.lines().next().unwrap_or_default();
+
+        let payload = match format {
+            LegacyPingFormat::Beta => format!(
+                "{}§{}§{}",
+                strip_formatting(motd),
+                self.online,
+                self.max
+            ),
+            LegacyPingFormat::V1_4 => format!(
+                "§1\0{}\0{}\0{}\0{}\0{}",
+                LEGACY_PROTOCOL_VERSION, self.version, motd, self.online, self.max
+            ),
+        };
+
+        let chars: Vec<u16> = payload.encode_utf16().collect();
+
+        let mut buf = Vec::with_capacity(3 + chars.len() * 2);
+        buf.push(LEGACY_KICK_PACKET_ID);
+        buf.extend_from_slice(&(chars.len() as u16).to_be_bytes());
+        for c in chars {
+            buf.extend_from_slice(&c.to_be_bytes());
+        }
+
+        buf
+    }
+}
+
+/// Removes `§x` formatting codes, since the beta format uses `§` as its
+/// field separator.
+fn strip_formatting(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(buf: &[u8]) -> String {
+        assert_eq!(buf[0], LEGACY_KICK_PACKET_ID);
+        let len = u16::from_be_bytes([buf[1], buf[2]]) as usize;
+        let chars: Vec<u16> = buf[3..]
+            .chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        assert_eq!(chars.len(), len);
+        String::from_utf16(&chars).unwrap()
+    }
+
+    fn response() -> LegacyPingResponse {
+        LegacyPingResponse {
+            version: "Lure".into(),
+            motd: "§dAnother Lure proxy\nsecond line".into(),
+            online: 3,
+            max: 4000,
+        }
+    }
+
+    #[test]
+    fn legacy_ping_detect() {
+        assert_eq!(LegacyPingFormat::detect(&[0xFE]), LegacyPingFormat::Beta);
+        assert_eq!(LegacyPingFormat::detect(&[0xFE, 0x01]), LegacyPingFormat::V1_4);
+        assert_eq!(
+            LegacyPingFormat::detect(&[0xFE, 0x01, 0xFA]),
+            LegacyPingFormat::V1_4
+        );
+    }
+
+    #[test]
+    fn legacy_ping_encode() {
+        assert_eq!(
+            decode(&response().encode(LegacyPingFormat::Beta)),
+            "Another Lure proxy§3§4000"
+        );
+        assert_eq!(
+            decode(&response().encode(LegacyPingFormat::V1_4)),
+            "§1\0127\0Lure\0§dAnother Lure proxy\03\04000"
+        );
+    }
+}
@@ -2,3 +2,4 @@ pub mod byte_channel;
 pub mod client_info;
 pub mod codec;
 pub mod connection;
+pub mod legacy_ping;
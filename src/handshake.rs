@@ -0,0 +1,78 @@
+use anyhow::{bail, ensure};
+
+use valence_protocol::packets::c2s::handshake::HandshakeOwned;
+
+/// Marker Forge clients append to the hostname, e.g. `\0FML2\0`.
+const FORGE_MARKER: char = '\0';
+
+/// Rejects nonsensical handshakes and normalises the hostname in place, so
+/// it can be used for host lookups as is.
+pub fn sanitize(handshake: &mut HandshakeOwned, max_hostname_length: usize) -> anyhow::Result<()> {
+    // Release protocol numbers start at 1, snapshots set bit 30 on top.
+    ensure!(
+        handshake.protocol_version.0 > 0,
+        "invalid protocol version {}",
+        handshake.protocol_version.0
+    );
+
+    ensure!(
+        handshake.server_address.len() <= max_hostname_length,
+        "hostname longer than {max_hostname_length} bytes"
+    );
+
+    handshake.server_address = normalize_hostname(&handshake.server_address)?;
+    Ok(())
+}
+
+/// Strips Forge markers, the port and the trailing dot of a hostname and
+/// lowercases it.
+pub fn normalize_hostname(raw: &str) -> anyhow::Result<String> {
+    let hostname = raw.split(FORGE_MARKER).next().unwrap_or_default();
+
+    let hostname = match hostname.strip_prefix('[') {
+        // IPv6 literal, optionally followed by a port.
+        Some(rest) => match rest.split_once(']') {
+            Some((address, _)) => address,
+            None => bail!("unterminated IPv6 hostname '{hostname}'"),
+        },
+        // Bare IPv6 literals have more colons and no port.
+        None => match hostname.rsplit_once(':') {
+            Some((host, port)) if !host.contains(':') && port.parse::<u16>().is_ok() => host,
+            _ => hostname,
+        },
+    };
+
+    let hostname = hostname.strip_suffix('.').unwrap_or(hostname);
+
+    ensure!(!hostname.is_empty(), "empty hostname");
+    ensure!(
+        hostname
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':')),
+        "invalid characters in hostname '{}'",
+        hostname.escape_debug()
+    );
+
+    Ok(hostname.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_normalize_hostname() {
+        let normalize = |raw| normalize_hostname(raw).unwrap();
+
+        assert_eq!(normalize("Play.Example.com"), "play.example.com");
+        assert_eq!(normalize("play.example.com."), "play.example.com");
+        assert_eq!(normalize("play.example.com:25565"), "play.example.com");
+        assert_eq!(normalize("play.example.com.\0FML2\0"), "play.example.com");
+        assert_eq!(normalize("[::1]:25565"), "::1");
+        assert_eq!(normalize("2001:DB8::25"), "2001:db8::25");
+
+        assert!(normalize_hostname("").is_err());
+        assert!(normalize_hostname("\0FML\0").is_err());
+        assert!(normalize_hostname("play example.com").is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use ipnet::IpNet;
+
+use crate::config::ListenerConfig;
+use crate::utils::{file_modified, parse_ip_net};
+
+/// Set of IP ranges, matched with one hash lookup per distinct prefix length
+/// so large lists stay cheap to check.
+#[derive(Debug, Default)]
+pub struct PrefixSet {
+    v4: BTreeMap<u8, HashSet<u32>>,
+    v6: BTreeMap<u8, HashSet<u128>>,
+}
+
+impl PrefixSet {
+    pub fn insert(&mut self, net: IpNet) {
+        match net.trunc() {
+            IpNet::V4(net) => {
+                let network = u32::from(net.network());
+                self.v4.entry(net.prefix_len()).or_default().insert(network);
+            }
+            IpNet::V6(net) => {
+                let network = u128::from(net.network());
+                self.v6.entry(net.prefix_len()).or_default().insert(network);
+            }
+        }
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match canonical_ip(ip) {
+            IpAddr::V4(ip) => {
+                let bits = u32::from(ip);
+                self.v4.iter().any(|(len, networks)| {
+                    let mask = u32::MAX.checked_shl(32 - *len as u32).unwrap_or(0);
+                    networks.contains(&(bits & mask))
+                })
+            }
+            IpAddr::V6(ip) => {
+                let bits = u128::from(ip);
+                self.v6.iter().any(|(len, networks)| {
+                    let mask = u128::MAX.checked_shl(128 - *len as u32).unwrap_or(0);
+                    networks.contains(&(bits & mask))
+                })
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.v4.is_empty() && self.v6.is_empty()
+    }
+
+    /// Parses a list of addresses and CIDR ranges, failing on the first
+    /// invalid entry.
+    pub fn parse<'a>(entries: impl IntoIterator<Item = &'a String>) -> anyhow::Result<Self> {
+        let mut set = Self::default();
+        for entry in entries {
+            let net =
+                parse_ip_net(entry).ok_or_else(|| anyhow!("invalid IP or CIDR range '{entry}'"))?;
+            set.insert(net);
+        }
+        Ok(set)
+    }
+}
+
+/// IPv4 clients of a dual stack listener show up as IPv4-mapped IPv6
+/// addresses, which should match IPv4 ranges.
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// Allow and deny ranges of a listener.
+#[derive(Debug)]
+pub struct ListenerFilter {
+    allow: PrefixSet,
+    deny: PrefixSet,
+}
+
+impl ListenerFilter {
+    pub fn new(config: &ListenerConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            allow: PrefixSet::parse(&config.allow)?,
+            deny: PrefixSet::parse(&config.deny)?,
+        })
+    }
+
+    /// Whether the address is in the allow list, if there is one, and not
+    /// in the deny list.
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        (self.allow.is_empty() || self.allow.contains(ip)) && !self.deny.contains(ip)
+    }
+}
+
+/// Network-wide denied ranges, reloaded whenever the deny list file changes.
+/// The file lists one address or CIDR range per line, `#` starts a comment.
+#[derive(Debug)]
+pub struct DenyList {
+    path: PathBuf,
+    inner: RwLock<(Option<SystemTime>, PrefixSet)>,
+}
+
+impl DenyList {
+    pub fn new(path: &str) -> Self {
+        let deny_list = Self {
+            path: PathBuf::from(path),
+            inner: RwLock::new(Default::default()),
+        };
+
+        if !deny_list.path.exists() {
+            println!(
+                "Deny list {} does not exist, creating an empty one.",
+                deny_list.path.display()
+            );
+            if let Err(e) = fs::write(&deny_list.path, "") {
+                eprintln!(
+                    "Failed to create deny list {}: {e}",
+                    deny_list.path.display()
+                );
+            }
+        }
+
+        deny_list.refresh();
+        deny_list
+    }
+
+    /// Reloads the deny list if the file was modified since the last check.
+    /// The previous ranges are kept if the file can't be read.
+    pub fn refresh(&self) {
+        let modified = file_modified(&self.path);
+        if self.inner.read().unwrap().0 == modified {
+            return;
+        }
+
+        let raw = match fs::read_to_string(&self.path) {
+            Ok(raw) => raw,
+            Err(e) => {
+                eprintln!("Failed to load deny list {}: {e}", self.path.display());
+                self.inner.write().unwrap().0 = modified;
+                return;
+            }
+        };
+
+        let mut ranges = PrefixSet::default();
+        let mut count = 0;
+
+        for line in raw.lines() {
+            let entry = line.split('#').next().unwrap_or_default().trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            match parse_ip_net(entry) {
+                Some(net) => {
+                    ranges.insert(net);
+                    count += 1;
+                }
+                None => eprintln!("Ignoring invalid IP or CIDR range '{entry}' in deny list."),
+            }
+        }
+
+        *self.inner.write().unwrap() = (modified, ranges);
+        println!("Loaded {count} denied ranges from {}.", self.path.display());
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.inner.read().unwrap().1.contains(ip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipfilter_prefix_matching() {
+        let entries = ["10.0.0.0/8", "192.168.1.7", "2001:db8::/32", "0.0.0.0/0"].map(String::from);
+        let all = PrefixSet::parse(&entries).unwrap();
+        let set = PrefixSet::parse(&entries[..3]).unwrap();
+
+        assert!(set.contains("10.20.30.40".parse().unwrap()));
+        assert!(set.contains("192.168.1.7".parse().unwrap()));
+        assert!(!set.contains("192.168.1.8".parse().unwrap()));
+        assert!(set.contains("2001:db8::1".parse().unwrap()));
+        assert!(!set.contains("2001:db9::1".parse().unwrap()));
+        assert!(set.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(all.contains("8.8.8.8".parse().unwrap()));
+
+        assert!(PrefixSet::parse(&["10.0.0.0/33".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+
+use anyhow::Context;
+
+use crate::config::ListenerConfig;
+use crate::ipfilter::ListenerFilter;
+use crate::routing::{HostPattern, Route};
+
+/// Settings of one bound address, with its filters and scope compiled.
+#[derive(Debug)]
+pub struct Listener {
+    pub bind: SocketAddr,
+    pub config: ListenerConfig,
+    filter: ListenerFilter,
+    hosts: Vec<HostPattern>,
+    servers: HashSet<String>,
+}
+
+impl Listener {
+    pub fn new(config: &ListenerConfig) -> anyhow::Result<Self> {
+        let hosts = config
+            .hosts
+            .iter()
+            .map(|host| HostPattern::parse(host))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            bind: config
+                .bind
+                .parse()
+                .with_context(|| format!("invalid listener address '{}'", config.bind))?,
+            config: config.to_owned(),
+            filter: ListenerFilter::new(config)?,
+            hosts,
+            servers: config.servers.iter().cloned().collect(),
+        })
+    }
+
+    /// Whether the address may connect. Behind the PROXY protocol this is
+    /// the load balancer, not the client.
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        self.filter.allows(ip)
+    }
+
+    /// Whether players joining through `hostname` may take `route` from this
+    /// listener.
+    pub fn reaches(&self, hostname: &str, route: &Route) -> bool {
+        let hostname = hostname.to_ascii_lowercase();
+
+        (self.hosts.is_empty() || self.hosts.iter().any(|host| host.matches(&hostname)))
+            && (self.servers.is_empty() || self.servers.contains(&route.server))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listener_scope() {
+        let listener = Listener::new(&ListenerConfig {
+            hosts: vec!["*.staff.example.com".to_string()],
+            servers: vec!["admin".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        let route = |server: &str| Route {
+            rule: "*".to_string(),
+            server: server.to_string(),
+        };
+
+        assert!(listener.reaches("eu.staff.example.com", &route("admin")));
+        assert!(!listener.reaches("eu.staff.example.com", &route("lobby")));
+        assert!(!listener.reaches("play.example.com", &route("admin")));
+        assert!(Listener::new(&Default::default())
+            .unwrap()
+            .reaches("play.example.com", &route("lobby")));
+    }
+}
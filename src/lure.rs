@@ -1,31 +1,30 @@
 use std::borrow::Cow;
-use std::fs;
-use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{bail, ensure, Context};
 
-use base64::engine::general_purpose;
-use base64::Engine;
-
 use num::BigInt;
 
 use reqwest::StatusCode;
 use rsa::Pkcs1v15Encrypt;
 
 use serde::Deserialize;
-use serde_json::json;
 
 use sha1::digest::Update;
 use sha1::Sha1;
 use sha2::{Digest, Sha256};
 
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::Semaphore;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
 
 use tokio::task::JoinHandle;
 
+use tracing::{error, info, warn};
+
 use valence::prelude::*;
 
 use valence_protocol::packets::c2s::handshake::{Handshake, HandshakeOwned};
@@ -39,12 +38,38 @@ use valence_protocol::packets::{C2sPlayPacket, S2cPlayPacket};
 use valence_protocol::types::{HandshakeNextState, Property};
 use valence_protocol::{translation_key, VarInt};
 
-use crate::config::LureConfig;
+use crate::antibot::AntiBot;
+use crate::bans::{Ban, BanList};
+use crate::config::{ConfigSource, ForwardingMode, LureConfig, ServerConfig};
 use crate::connection::client_info::ClientInfo;
 use crate::connection::codec::{PacketDecoder, PacketEncoder};
 use crate::connection::connection::Connection;
+use crate::connection::legacy_ping::{
+    LegacyPingFormat, LegacyPingResponse, LEGACY_PING_PACKET_ID,
+};
+use crate::handshake;
+use crate::ipfilter::DenyList;
 use crate::keypair::KeyPair;
-use crate::utils::read_favicon;
+use crate::listener::Listener;
+use crate::metrics::Metrics;
+use crate::players::{PlayerGuard, PlayerRegistry};
+use crate::proxy_protocol;
+use crate::ratelimit::PacketRateLimiter;
+use crate::resolver::BackendResolver;
+use crate::routing::{HostRouter, Route};
+use crate::status::StatusCache;
+use crate::throttle::{ConnectionThrottle, ThrottleGuard, ThrottleKind};
+use crate::usernames::UsernamePolicy;
+use crate::utils::file_modified;
+use crate::validation;
+use crate::whitelist::Whitelist;
+
+/// How often the config, favicon, whitelist, ban and deny list files are
+/// checked for changes and idle throttle and anti-bot entries are cleaned up.
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often changed metrics are logged.
+const METRICS_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Deserialize)]
 pub struct GameProfile {
@@ -56,110 +81,365 @@ pub struct GameProfile {
 #[derive(Clone, Debug)]
 pub struct Lure {
     config: LureConfig,
-    favicon: Option<String>,
+    status: Arc<StatusCache>,
+    whitelist: Option<Arc<Whitelist>>,
+    bans: Arc<BanList>,
+    listeners: Arc<Vec<Arc<Listener>>>,
+    deny_list: Arc<DenyList>,
+    router: Arc<HostRouter>,
+    throttle: Arc<ConnectionThrottle>,
+    antibot: Arc<AntiBot>,
+    usernames: Arc<UsernamePolicy>,
+    players: Arc<PlayerRegistry>,
+    metrics: Arc<Metrics>,
+    resolver: Arc<BackendResolver>,
     keypair: KeyPair,
 }
 
 impl Lure {
-    pub fn new(config: LureConfig) -> Lure {
-        Lure {
+    pub fn new(config: LureConfig) -> anyhow::Result<Lure> {
+        Self::build(config, KeyPair::new())
+    }
+
+    fn build(config: LureConfig, keypair: KeyPair) -> anyhow::Result<Lure> {
+        validation::validate(&config).check()?;
+
+        let restricted_servers = config.servers.values().any(|server| server.restricted);
+
+        Ok(Lure {
+            status: Arc::new(StatusCache::new(&config.proxy, &config.servers)),
+            whitelist: (config.whitelist.is_used() || restricted_servers)
+                .then(|| Arc::new(Whitelist::new(&config.whitelist.file))),
+            bans: Arc::new(BanList::new(&config.bans.file)),
+            listeners: Arc::new(
+                config
+                    .listener
+                    .iter()
+                    .map(|listener| Listener::new(listener).map(Arc::new))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            deny_list: Arc::new(DenyList::new(&config.deny_list.file)),
+            router: Arc::new(HostRouter::new(&config.hosts)?),
+            throttle: Arc::new(ConnectionThrottle::new(&config.throttle)),
+            antibot: Arc::new(AntiBot::new(&config.antibot)),
+            usernames: Arc::new(UsernamePolicy::new(&config.usernames)?),
+            players: Arc::new(PlayerRegistry::default()),
+            metrics: Arc::new(Metrics::default()),
+            resolver: Arc::new(BackendResolver::new()?),
             config,
-            favicon: None,
-            keypair: KeyPair::new(),
-        }
+            keypair,
+        })
     }
 
-    pub fn get_default_server(&self, hostname: &str) -> Option<String> {
-        let hosts = self.config.hosts.clone();
+    /// Route chosen for `hostname` in the `hosts` table, if it's reachable
+    /// through `listener`.
+    pub fn route(&self, listener: &Listener, hostname: &str) -> Option<Route> {
+        self.router
+            .route(hostname)
+            .filter(|route| listener.reaches(hostname, route))
+    }
 
-        let host = if hosts.contains_key(hostname) {
-            hosts.get(hostname)
-        } else {
-            hosts.get("*")
-        };
+    pub fn get_default_server(&self, listener: &Listener, hostname: &str) -> Option<String> {
+        self.route(listener, hostname).map(|route| route.server)
+    }
 
-        host.map(|h| h.to_owned())
+    pub fn get_server(&self, name: &str) -> Option<&ServerConfig> {
+        self.config.servers.get(name)
     }
 
-    pub fn get_server(&self, name: &str) -> Option<String> {
-        let servers = self.config.servers.clone();
-        servers.get(name)
-            .map(|sv| sv.to_owned())
+    /// Server players joining through `hostname` are sent to.
+    pub fn get_host_server(&self, listener: &Listener, hostname: &str) -> Option<&ServerConfig> {
+        self.get_server(&self.get_default_server(listener, hostname)?)
     }
 
-    pub fn get_favicon(&self) -> Option<String> {
-        let favicon = &self.config.proxy.favicon;
-        let favicon_file = PathBuf::from(favicon);
+    pub async fn start(&mut self, source: &ConfigSource) -> anyhow::Result<()> {
+        let mut maintenance = self.spawn_maintenance();
 
-        if !favicon_file.exists() {
-            return None;
-        }
+        let metrics = self.metrics.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(METRICS_INTERVAL);
+            loop {
+                interval.tick().await;
+                metrics.report();
+            }
+        });
 
-        match fs::read(favicon_file) {
-            Ok(favicon) => {
-                let favicon_meta = image_meta::load_from_buf(&favicon).ok()?;
-                if favicon_meta.dimensions.width != 64 || favicon_meta.dimensions.height != 64 {
-                    return None;
-                };
+        let mut reload_rx = spawn_reload_triggers(source)?;
 
-                let mut buf = "data:image/png;base64,".to_string();
-                general_purpose::STANDARD.encode_string(favicon, &mut buf);
-                
-                Some(buf)
-            },
-            Err(_) => None,
+        // Start listeners, each accepting up to its own connection limit.
+        let (accept_tx, mut accept_rx) = mpsc::channel(64);
+        for listener in self.listeners.iter() {
+            info!("Preparing socket {}", listener.bind);
+            let socket = TcpListener::bind(listener.bind).await?;
+            let semaphore = Arc::new(Semaphore::new(listener.config.max_connections));
+            let accept_tx = accept_tx.clone();
+            let bind = listener.bind;
+
+            tokio::spawn(async move {
+                while let Ok(permit) = semaphore.clone().acquire_owned().await {
+                    match socket.accept().await {
+                        Ok((client, address)) => {
+                            let accepted = (bind, client, address, permit);
+                            if accept_tx.send(accepted).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => warn!("Failed to accept a connection on {bind}: {e}"),
+                    }
+                }
+            });
+        }
+        drop(accept_tx);
+
+        info!("Starting Lure server.");
+        loop {
+            tokio::select! {
+                Some((bind, client, address, permit)) = accept_rx.recv() => {
+                    self.accept(bind, client, address, permit);
+                }
+                Some(()) = reload_rx.recv() => {
+                    if self.reload(source).await {
+                        maintenance.abort();
+                        maintenance = self.spawn_maintenance();
+                    }
+                }
+                else => break,
+            }
         }
+
+        Ok(())
     }
 
-    pub async fn start(&mut self) -> anyhow::Result<()> {
-        // Listener config.
-        let listener_cfg = self.config.listener.to_owned();
-        println!("Preparing socket {}", listener_cfg.bind);
-        let address: SocketAddr = listener_cfg.bind.parse()?;
-        let max_connections = listener_cfg.max_connections;
+    fn accept(
+        &self,
+        bind: SocketAddr,
+        mut client: TcpStream,
+        peer: SocketAddr,
+        permit: OwnedSemaphorePermit,
+    ) {
+        self.metrics.connections.inc();
+
+        let listener = match self.listeners.iter().find(|listener| listener.bind == bind) {
+            Some(listener) => listener.clone(),
+            None => return,
+        };
+        if !listener.allows(peer.ip()) {
+            self.metrics.denied_connections.inc();
+            return;
+        }
 
-        // Load favicon.
-        let proxy_cfg = self.config.proxy.to_owned();
-        let favicon_path = proxy_cfg.favicon;
-        self.favicon = read_favicon(favicon_path);
+        // Without a PROXY header the peer is the client, so it is checked
+        // before spawning anything.
+        let admitted = if listener.config.proxy_protocol {
+            None
+        } else {
+            match self.admit(peer.ip()) {
+                Some(admitted) => Some(admitted),
+                None => return,
+            }
+        };
 
-        // Start server.
-        let listener = TcpListener::bind(address).await?;
-        let semaphore = Arc::new(Semaphore::new(max_connections));
+        let lure = self.clone();
+        tokio::spawn(async move {
+            let (address, (ban, throttle_guard)) = match admitted {
+                Some(admitted) => (peer, admitted),
+                None => {
+                    let address = match lure.client_address(&listener, &mut client, peer).await {
+                        Ok(address) => address,
+                        Err(e) => {
+                            info!("Connection from {peer} ended with: {e:#}");
+                            return;
+                        }
+                    };
+                    match lure.admit(address.ip()) {
+                        Some(admitted) => (address, admitted),
+                        None => return,
+                    }
+                }
+            };
 
-        while let core::result::Result::Ok(permit) = semaphore.clone().acquire_owned().await {
-            let (client, remote_client_addr) = listener.accept().await?;
-            eprintln!("Accepted connection to {remote_client_addr}");
+            info!("Accepted connection to {address}");
 
             if let Err(e) = client.set_nodelay(true) {
-                eprintln!("Failed to set TCP_NODELAY: {e}");
+                warn!("Failed to set TCP_NODELAY: {e}");
             }
 
-            let lure = self.clone();
-            tokio::spawn(async move {
-                if let Err(e) = lure.handle_connection(client, remote_client_addr).await {
-                    eprintln!("Connection to {remote_client_addr} ended with: {e:#}");
-                } else {
-                    eprintln!("Connection to {remote_client_addr} ended.");
+            if let Err(e) = lure.handle_connection(client, address, &listener, ban).await {
+                info!("Connection to {address} ended with: {e:#}");
+            } else {
+                info!("Connection to {address} ended.");
+            }
+
+            drop(throttle_guard);
+            drop(permit);
+        });
+    }
+
+    /// Checks a client against the deny list, IP bans and the throttle,
+    /// returning its ban and throttle slot if it may go on. Banned IPs are
+    /// let through to show logins why.
+    fn admit(&self, ip: IpAddr) -> Option<(Option<Ban>, ThrottleGuard)> {
+        if self.deny_list.contains(ip) {
+            self.metrics.denied_connections.inc();
+            return None;
+        }
+        let ban = self.bans.find_ip(ip);
+        if ban.is_some() {
+            self.metrics.denied_connections.inc();
+        }
+
+        // Drop throttled IPs before reading anything else from them.
+        match self.throttle.try_accept(ip) {
+            Some(guard) => Some((ban, guard)),
+            None => {
+                self.metrics.throttled_connections.inc();
+                None
+            }
+        }
+    }
+
+    /// Address of the client, taken from the PROXY protocol header on
+    /// listeners behind a load balancer.
+    async fn client_address(
+        &self,
+        listener: &Listener,
+        client: &mut TcpStream,
+        peer: SocketAddr,
+    ) -> anyhow::Result<SocketAddr> {
+        if !listener.config.proxy_protocol {
+            return Ok(peer);
+        }
+
+        let handshake_timeout = Duration::from_millis(self.config.timeouts.handshake_ms);
+        let header = with_timeout(
+            "PROXY header",
+            handshake_timeout,
+            proxy_protocol::read_header(client),
+        );
+        match header.await {
+            Ok(address) => Ok(address.unwrap_or(peer)),
+            Err(e) => {
+                self.metrics.rejected_handshakes.inc();
+                Err(e.context("invalid PROXY header"))
+            }
+        }
+    }
+
+    /// Watches favicon, whitelist, bans and deny list for changes, and
+    /// cleans up throttle and anti-bot entries.
+    fn spawn_maintenance(&self) -> JoinHandle<()> {
+        let status = self.status.clone();
+        let whitelist = self.whitelist.clone();
+        let bans = self.bans.clone();
+        let deny_list = self.deny_list.clone();
+        let throttle = self.throttle.clone();
+        let antibot = self.antibot.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+            loop {
+                interval.tick().await;
+                status.refresh();
+                if let Some(whitelist) = &whitelist {
+                    whitelist.refresh();
                 }
+                bans.refresh();
+                deny_list.refresh();
+                throttle.cleanup();
+                antibot.cleanup();
+            }
+        })
+    }
 
-                drop(permit);
-            });
+    /// Loads the config file again and swaps in everything built from it,
+    /// so new connections use the new config. Running sessions keep the
+    /// config they started with, and players, metrics, the key pair and
+    /// unchanged throttle and anti-bot state carry over. On errors the
+    /// current config stays in place.
+    async fn reload(&mut self, source: &ConfigSource) -> bool {
+        let config_path = &source.path;
+        info!("Reloading {config_path}");
+
+        // Reading the config and the files it names blocks, so the new state
+        // is built off the runtime threads.
+        let rebuild_source = source.to_owned();
+        let keypair = self.keypair.clone();
+        let reloaded = tokio::task::spawn_blocking(move || {
+            let config = rebuild_source.load()?;
+            Lure::build(config, keypair)
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|reloaded| reloaded);
+        let mut reloaded = match reloaded {
+            Ok(reloaded) => reloaded,
+            Err(e) => {
+                error!("Failed to reload {config_path}, keeping the current config: {e:#}");
+                return false;
+            }
+        };
+
+        // Sockets stay bound, so listeners carry their new settings over by
+        // address, and adding or removing listeners waits for a restart.
+        let mut listeners = Vec::new();
+        let mut restart = reloaded.listeners.len() != self.listeners.len();
+        for current in self.listeners.iter() {
+            let same_bind = |new: &&Arc<Listener>| new.bind == current.bind;
+            match reloaded.listeners.iter().find(same_bind) {
+                Some(new) => {
+                    restart |= new.config.max_connections != current.config.max_connections;
+                    listeners.push(new.clone());
+                }
+                None => {
+                    restart = true;
+                    listeners.push(current.clone());
+                }
+            }
         }
+        if restart {
+            info!("Changes to listener binds and max_connections apply after a restart.");
+        }
+        reloaded.listeners = Arc::new(listeners);
 
-        println!("Starting Lure server.");
-        core::result::Result::Ok(())
+        if reloaded.config.throttle == self.config.throttle {
+            reloaded.throttle = self.throttle.clone();
+        }
+        if reloaded.config.antibot == self.config.antibot {
+            reloaded.antibot = self.antibot.clone();
+        }
+        reloaded.players = self.players.clone();
+        reloaded.metrics = self.metrics.clone();
+        reloaded.resolver = self.resolver.clone();
+
+        *self = reloaded;
+        info!("Reloaded {config_path}");
+        true
     }
 
     pub async fn handle_connection(
         &self,
         client_socket: TcpStream,
         address: SocketAddr,
+        listener: &Listener,
+        ban: Option<Ban>,
     ) -> anyhow::Result<()> {
+        // Clients older than 1.7 open with 0xFE instead of a handshake.
+        let handshake_timeout = Duration::from_millis(self.config.timeouts.handshake_ms);
+        let mut first_byte = [0u8; 1];
+        let peeked = with_timeout("handshake", handshake_timeout, async {
+            Ok(client_socket.peek(&mut first_byte).await?)
+        })
+        .await?;
+        if peeked == 1 && first_byte[0] == LEGACY_PING_PACKET_ID {
+            if let Some(ban) = ban {
+                bail!("{} is banned: {}", address.ip(), ban.reason);
+            }
+            return self.handle_legacy_ping(client_socket, address).await;
+        }
+
         // Client state
         let (client_read, client_write) = client_socket.into_split();
 
-        let connection = Connection {
+        let mut connection = Connection {
             address,
             enc: PacketEncoder::new(),
             dec: PacketDecoder::new(),
@@ -167,21 +447,87 @@ impl Lure {
             write: client_write,
             buf: String::new(),
         };
+        connection.dec.set_limits(self.config.limits.handshake);
 
-        self.handle_handshake(connection).await?;
+        self.handle_handshake(connection, listener, ban).await?;
         Ok(())
     }
 
-    pub async fn handle_handshake(&self, mut connection: Connection) -> anyhow::Result<()> {
+    pub async fn handle_handshake(
+        &self,
+        mut connection: Connection,
+        listener: &Listener,
+        ban: Option<Ban>,
+    ) -> anyhow::Result<()> {
         // Wait for initial handshake.
-        let handshake: HandshakeOwned = connection.recv().await?;
+        let handshake_timeout = Duration::from_millis(self.config.timeouts.handshake_ms);
+        let handshake = with_timeout(
+            "handshake",
+            handshake_timeout,
+            connection.recv::<HandshakeOwned>(),
+        );
+        let mut handshake = match handshake.await {
+            Ok(handshake) => handshake,
+            Err(e) => {
+                self.metrics.rejected_handshakes.inc();
+                return Err(e.context("invalid handshake"));
+            }
+        };
+
+        let max_hostname_length = listener.config.max_hostname_length;
+        if let Err(e) = handshake::sanitize(&mut handshake, max_hostname_length) {
+            self.metrics.rejected_handshakes.inc();
+            return Err(e.context("rejected handshake"));
+        }
+
+        // Banned IPs only get as far as the handshake, so logins can show why.
+        let ip = connection.address.ip();
+        if let Some(ban) = ban {
+            if handshake.next_state == HandshakeNextState::Login {
+                self.disconnect_banned(&mut connection, &ban).await?;
+            }
+            bail!("{ip} is banned: {}", ban.reason);
+        }
+
+        let kind = match handshake.next_state {
+            HandshakeNextState::Status => ThrottleKind::Status,
+            HandshakeNextState::Login => ThrottleKind::Login,
+        };
+
+        if !self.throttle.allow(ip, kind) {
+            if kind == ThrottleKind::Login {
+                connection
+                    .send(&DisconnectLogin {
+                        reason: self.config.throttle.message.to_owned().into_text().into(),
+                    })
+                    .await?;
+            }
+            bail!("too many {kind:?} requests from {ip}");
+        }
+
+        let limits = &self.config.limits;
+        connection.dec.set_limits(match handshake.next_state {
+            HandshakeNextState::Status => limits.status,
+            HandshakeNextState::Login => limits.login,
+        });
+
+        let timeouts = &self.config.timeouts;
         match handshake.next_state {
-            HandshakeNextState::Status => self.handle_status(&mut connection, handshake).await,
-            HandshakeNextState::Login => match self.handle_login(&mut connection, handshake).await?
+            HandshakeNextState::Status => {
+                let status = self.handle_status(&mut connection, handshake, listener);
+                with_timeout("status", Duration::from_millis(timeouts.status_ms), status).await
+            }
+            HandshakeNextState::Login => match with_timeout(
+                "login",
+                Duration::from_millis(timeouts.login_ms),
+                self.handle_login(&mut connection, handshake, listener),
+            )
+            .await?
             {
-                Some(info) => {
+                Some((info, _player)) => {
+                    connection.dec.set_limits(limits.play);
                     // let mut client = connection.into_client(info, 2097152, 8388608);
-                    self.handle_play(connection, info).await?;
+                    self.handle_play(connection, info, listener).await?;
                     Ok(())
                 }
                 None => Ok(()),
@@ -189,45 +535,58 @@ impl Lure {
         }
     }
 
+    pub async fn handle_legacy_ping(
+        &self,
+        mut client_socket: TcpStream,
+        address: SocketAddr,
+    ) -> anyhow::Result<()> {
+        // 1.6 clients append a MC|PingHost message, which carries nothing we need.
+        let mut request = [0u8; 512];
+        let status_timeout = Duration::from_millis(self.config.timeouts.status_ms);
+        let read = with_timeout("status", status_timeout, async {
+            Ok(client_socket.read(&mut request).await?)
+        })
+        .await?;
+        let format = LegacyPingFormat::detect(&request[..read]);
+
+        let ip = address.ip();
+        if !self.throttle.allow(ip, ThrottleKind::Status) {
+            bail!("too many status pings from {ip}");
+        }
+
+        let proxy = &self.config.proxy;
+        let response = LegacyPingResponse {
+            version: "Lure".to_string(),
+            motd: proxy.motd.to_owned(),
+            online: 0,
+            max: proxy.max_players,
+        };
+
+        client_socket.write_all(&response.encode(format)).await?;
+        client_socket.shutdown().await?;
+        Ok(())
+    }
+
     pub async fn handle_status(
         &self,
         client: &mut Connection,
         handshake: HandshakeOwned,
+        listener: &Listener,
     ) -> anyhow::Result<()> {
         client.recv::<StatusRequest>().await?;
 
-        let proxy = self.config.proxy.to_owned();
-        let max_players = proxy.max_players;
-        let motd: Text = proxy.motd.into();
         let protocol = handshake.protocol_version.0;
-        let favicon = if let Some(favicon) = self.get_favicon() {
-            favicon
-        } else {
-            "".to_string()
-        };
+        let range = self.config.protocol.range(&handshake.server_address);
 
-        let json = json!({
-            "version": {
-                "name": "Lure",
-                "protocol": protocol
-            },
-            "players": {
-                "online": 0,
-                "max": max_players,
-                "sample": vec![PlayerSampleEntry {
-                    name: "foobar".into(),
-                    id: Uuid::from_u128(12345),
-                }],
-            },
-            "description": motd,
-            "favicon": favicon
-        });
+        let server = self.get_default_server(listener, &handshake.server_address);
+        let server = server.as_deref();
 
-        client
-            .send(&StatusResponse {
-                json: &json.to_string(),
-            })
-            .await?;
+        let json = if range.contains(protocol) {
+            self.status.response_json("Lure", protocol, server)
+        } else {
+            self.status.response_json(&range.version_name, range.max_version, server)
+        };
+        client.send(&StatusResponse { json: &json }).await?;
 
         let PingRequest { payload } = client.recv::<PingRequest>().await?;
         client.send(&PingResponse { payload }).await?;
@@ -238,17 +597,64 @@ impl Lure {
         &self,
         client: &mut Connection,
         handshake: HandshakeOwned,
-    ) -> anyhow::Result<Option<ClientInfo>> {
+        listener: &Listener,
+    ) -> anyhow::Result<Option<(ClientInfo, PlayerGuard)>> {
         let proxy_config = self.config.proxy.to_owned();
         let online_mode = proxy_config.online_mode;
-        let compression = proxy_config.compression_threshold;
+        let server = self.get_host_server(listener, &handshake.server_address);
+        let compression = server
+            .and_then(|server| server.compression_threshold)
+            .unwrap_or(proxy_config.compression_threshold);
 
         let LoginStart {
             username,
             profile_id: _,
         } = client.recv::<LoginStart>().await?;
-
         let username = username.to_owned_username();
+
+        let protocol = handshake.protocol_version.0;
+        let range = self.config.protocol.range(&handshake.server_address);
+
+        if !range.contains(protocol) {
+            let message = if protocol < range.min_version {
+                &self.config.protocol.outdated_client_message
+            } else {
+                &self.config.protocol.outdated_server_message
+            };
+            let reason = message.replace("{version}", &range.version_name);
+
+            client
+                .send(&DisconnectLogin {
+                    reason: reason.into_text().into(),
+                })
+                .await?;
+            bail!("unsupported protocol version {protocol}");
+        }
+
+        if let Err(violation) = self.usernames.check(username.as_str()) {
+            client
+                .send(&DisconnectLogin {
+                    reason: self.usernames.message(violation).into_text().into(),
+                })
+                .await?;
+            bail!("username {username} rejected: {violation:?}");
+        }
+
+        // Reserved before logging in, so two logins can't both take a name.
+        let deny_case_collisions = self.config.usernames.deny_case_collisions;
+        let player = match self.players.register(username.as_str(), deny_case_collisions) {
+            Some(player) => player,
+            None => {
+                let reason = self.config.usernames.collision_message.to_owned();
+                client
+                    .send(&DisconnectLogin {
+                        reason: reason.into_text().into(),
+                    })
+                    .await?;
+                bail!("username {username} collides with an online player");
+            }
+        };
+
         let mut info = if online_mode {
             self.login_online(client, username).await?
         } else {
@@ -258,6 +664,34 @@ impl Lure {
         info.protocol_version = handshake.protocol_version.0;
         info.hostname = handshake.server_address;
 
+        if let Some(ban) = self.bans.find_account(info.uuid, info.username.as_str()) {
+            self.disconnect_banned(client, &ban).await?;
+            bail!("{} is banned: {}", info.username, ban.reason);
+        }
+
+        let restricted = server.is_some_and(|server| server.restricted);
+        if let Some(whitelist) = &self.whitelist {
+            if (self.config.whitelist.is_enabled(&info.hostname) || restricted)
+                && !whitelist.contains(info.uuid, info.username.as_str())
+            {
+                client
+                    .send(&DisconnectLogin {
+                        reason: self.config.whitelist.message.to_owned().into_text().into(),
+                    })
+                    .await?;
+                bail!("{} is not whitelisted", info.username);
+            }
+        }
+
+        if !self.antibot.verify(info.ip, info.uuid) {
+            client
+                .send(&DisconnectLogin {
+                    reason: self.config.antibot.message.to_owned().into_text().into(),
+                })
+                .await?;
+            bail!("{} has to reconnect to verify", info.username);
+        }
+
         if compression > 0 {
             client
                 .send(&SetCompression {
@@ -275,7 +709,16 @@ impl Lure {
             })
             .await?;
 
-        Ok(Some(info))
+        Ok(Some((info, player)))
+    }
+
+    async fn disconnect_banned(&self, client: &mut Connection, ban: &Ban) -> anyhow::Result<()> {
+        let reason = ban.format_message(&self.config.bans.message);
+        client
+            .send(&DisconnectLogin {
+                reason: reason.into_text().into(),
+            })
+            .await
     }
 
     async fn login_online(
@@ -383,59 +826,73 @@ impl Lure {
         })
     }
 
+    /// Connects to the first reachable address of a backend server,
+    /// returning the address it connected to.
+    async fn connect(&self, server: &ServerConfig) -> anyhow::Result<(TcpStream, SocketAddr)> {
+        let address = server.backend_address()?;
+        let connect_timeout = Duration::from_millis(self.config.timeouts.connect_ms);
+        let mut last_error = None;
+
+        for candidate in self.resolver.resolve(&address).await? {
+            let connect = async { Ok(TcpStream::connect(candidate).await?) };
+            match with_timeout("connect", connect_timeout, connect).await {
+                Ok(stream) => return Ok((stream, candidate)),
+                Err(e) => {
+                    warn!("Failed to connect to {candidate} for {address}: {e:#}");
+                    last_error = Some(e.context(format!("failed to connect to {candidate}")));
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("{address} has no addresses")))
+    }
+
     pub async fn handle_play(
         &self,
         mut client: Connection,
         info: ClientInfo,
+        listener: &Listener,
     ) -> anyhow::Result<()> {
-        let default_server = self.get_default_server(&info.hostname);
-
-        if default_server.is_none() {
-            client
-                .disconnect("No host found".into_text().color(Color::RED))
-                .await?;
-            bail!("No host found");
-        }
-
-        let default_server = default_server.unwrap_or_default();
-        let default_server_addr = self.get_server(&default_server);
-
-        if default_server_addr.is_none() {
-            let error = format!(
-                "Default server {} for host {} doesnt exist.",
-                &default_server,
-                &info.hostname
-            );
-            client
-                .disconnect(error.clone().into_text().color(Color::RED))
-                .await?;
-            bail!(error);
-        }
-
-        let server_address: SocketAddr = default_server_addr
-            .unwrap_or_default()
-            .replace("\"", "")
-            .parse()
-            .to_owned()?;
-        let connect_result = TcpStream::connect(server_address).await;
+        let route = match self.route(listener, &info.hostname) {
+            Some(route) => route,
+            None => {
+                client
+                    .disconnect("No host found".into_text().color(Color::RED))
+                    .await?;
+                bail!("No host found");
+            }
+        };
+        info!("Routing {} from {} to {route}", info.username, info.hostname);
 
-        let server_stream: TcpStream = match TcpStream::connect(server_address).await {
-            Ok(stream) => stream,
-            Err(_) => {
+        let default_server = route.server;
+        let server_config = match self.get_server(&default_server) {
+            Some(server_config) => server_config,
+            None => {
                 let error = format!(
-                    "Cannot connect to server {}:\n\n{:?}",
+                    "Default server {} for host {} doesnt exist.",
                     &default_server,
-                    connect_result.err()
+                    &info.hostname
                 );
                 client
                     .disconnect(error.clone().into_text().color(Color::RED))
                     .await?;
                 bail!(error);
+            }
+        };
+
+        let (server_stream, server_address) = match self.connect(server_config).await {
+            Ok(connected) => connected,
+            Err(e) => {
+                let error = format!("Cannot connect to server {}:\n\n{e:#}", &default_server);
+                client
+                    .disconnect(error.clone().into_text().color(Color::RED))
+                    .await?;
+                bail!(error);
             },
         };
 
         if let Err(e) = server_stream.set_nodelay(true) {
-            eprintln!("Failed to set TCP_NODELAY: {e}");
+            warn!("Failed to set TCP_NODELAY: {e}");
         }
 
         let (server_read, server_write) = server_stream.into_split();
@@ -449,15 +906,31 @@ impl Lure {
             buf: String::new(),
         };
 
-        let handshake_server_address = match self.config.proxy.player_forward_mode.as_str() {
-            "bungeecord" => format!(
-                "{}\0{:?}\0{}\0{}",
-                server_address.ip().to_string(),
-                client.address.to_string().split(":").next(),
-                info.uuid,
-                serde_json::to_string(&info.properties)?
-            ),
-            &_ => server_address.ip().to_string(),
+        let forwarding = server_config
+            .forwarding
+            .unwrap_or(self.config.proxy.player_forward_mode);
+        let handshake_server_address = match forwarding {
+            ForwardingMode::None => server_address.ip().to_string(),
+            ForwardingMode::Bungeecord | ForwardingMode::Bungeeguard => {
+                let mut properties = info.properties.clone();
+                if let (ForwardingMode::Bungeeguard, Some(secret)) =
+                    (forwarding, &server_config.secret)
+                {
+                    properties.push(Property {
+                        name: "bungeeguard-token".to_string(),
+                        value: secret.to_owned(),
+                        signature: None,
+                    });
+                }
+
+                format!(
+                    "{}\0{}\0{}\0{}",
+                    server_address.ip(),
+                    client.address.ip(),
+                    info.uuid.as_simple(),
+                    serde_json::to_string(&properties)?
+                )
+            }
         };
 
         server
@@ -500,14 +973,49 @@ impl Lure {
             buf: String::new(),
         };
 
+        let idle_timeout = Duration::from_millis(self.config.timeouts.play_idle_ms);
+
+        let rate_limit = &self.config.rate_limit;
+        let mut limiter = rate_limit
+            .enabled
+            .then(|| PacketRateLimiter::new(rate_limit, info.protocol_version));
+        let rate_limit_message = rate_limit.message.to_owned();
+
+        // Kicks are sent by the server to client half, which owns the
+        // client's write half.
+        let (kick_tx, mut kick_rx) = mpsc::channel::<anyhow::Error>(1);
+
         let c2s_fut: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
             loop {
+                let (id, size) =
+                    with_timeout("client idle", idle_timeout, client_to_server.peek_packet())
+                        .await?;
+
+                if let Some(limiter) = &mut limiter {
+                    if let Err(e) = limiter.check(id, size) {
+                        let _ = kick_tx.send(e).await;
+                        kick_tx.closed().await;
+                        return Ok(());
+                    }
+                }
+
                 client_to_server.pipe::<C2sPlayPacket>().await?;
             }
         });
 
         let s2c_fut = async move {
             loop {
+                // Kicks are only taken while waiting for the next packet, as
+                // reading is cancel-safe but writing a piped packet is not.
+                let next = server_to_client.peek_packet();
+                tokio::select! {
+                    next = with_timeout("server idle", idle_timeout, next) => next?,
+                    Some(e) = kick_rx.recv() => {
+                        server_to_client.disconnect(rate_limit_message.into_text()).await?;
+                        return Err(e);
+                    }
+                };
+
                 server_to_client.pipe::<S2cPlayPacket>().await?;
             }
         };
@@ -518,3 +1026,55 @@ impl Lure {
         }
     }
 }
+
+/// Runs a connection stage, failing with the stage name if it takes longer
+/// than `limit`.
+async fn with_timeout<T>(
+    stage: &str,
+    limit: Duration,
+    stage_fut: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    match tokio::time::timeout(limit, stage_fut).await {
+        Ok(result) => result,
+        Err(_) => bail!("{stage} timed out after {limit:?}"),
+    }
+}
+
+/// Requests a config reload on SIGHUP and whenever the config file or the
+/// files it includes change, are added or are removed.
+fn spawn_reload_triggers(source: &ConfigSource) -> anyhow::Result<mpsc::Receiver<()>> {
+    let (reload_tx, reload_rx) = mpsc::channel(1);
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup())?;
+        let reload_tx = reload_tx.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                let _ = reload_tx.try_send(());
+            }
+        });
+    }
+
+    let source = source.clone();
+    let snapshot = move || -> Vec<_> {
+        let files = source.files().into_iter();
+        files.map(|path| (file_modified(&path), path)).collect()
+    };
+    tokio::spawn(async move {
+        let mut modified = snapshot();
+        let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let current = snapshot();
+            if current != modified {
+                modified = current;
+                let _ = reload_tx.try_send(());
+            }
+        }
+    });
+
+    Ok(reload_rx)
+}
@@ -1,47 +1,93 @@
+mod antibot;
+mod bans;
+mod cli;
 mod config;
 mod connection;
+mod handshake;
+mod ipfilter;
 mod keypair;
+mod listener;
 mod lure;
+mod metrics;
+mod packets;
+mod players;
+mod proxy_protocol;
+mod ratelimit;
+mod resolver;
+mod routing;
+mod status;
+mod throttle;
+mod usernames;
 mod utils;
+mod validation;
+mod whitelist;
 
-use anyhow::anyhow;
-use std::error::Error;
-use std::{env};
+use std::fs;
+use std::path::Path;
 
-use config::LureConfig;
-use lure::Lure;
+use anyhow::bail;
+use clap::Parser;
+use tracing::info;
 
-use crate::config::LureConfigLoadError;
+use cli::{Cli, Command};
+use config::{ConfigSource, LureConfig, DEFAULT_SETTINGS};
+use lure::Lure;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let current_dir = env::current_dir()?;
-    let config_file = current_dir.join("settings.toml");
-    let config_file_path = config_file.to_str().ok_or(anyhow!("Failed to get config file path"))?;
-
-    println!("{}", config_file_path);
-
-    let config = match LureConfig::load(config_file_path) {
-        Ok(config) => {
-            // Save config to fill missing fields
-            let _ = config.save(config_file_path);
-            Ok(config)
-        },
-        Err(error) => {
-            match error {
-                LureConfigLoadError::Io(_) => {
-                    // If config loading fails we generate a default config
-                    let default_config = LureConfig::default();
-                    // Save the config to disk
-                    let _ = default_config.save(config_file_path);
-                    Ok(default_config)
-                },
-                LureConfigLoadError::Parse(parse_error) => Err(parse_error)
-            }
-        },
-    };
-
-    let mut lure = Lure::new(config?);
-    lure.start().await?;
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    tracing_subscriber::fmt().with_max_level(cli.log_level).init();
+
+    let source = cli.config_source()?;
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(&source).await,
+        Command::Check => check(&source),
+        Command::Init { force } => init(&source.path, force),
+        Command::Fill => fill(&source.path),
+    }
+}
+
+async fn run(source: &ConfigSource) -> anyhow::Result<()> {
+    let config_file_path = source.path.as_str();
+    info!("Using config {config_file_path}");
+
+    if !Path::new(config_file_path).exists() {
+        info!("Config {config_file_path} not found, writing the default one.");
+        fs::write(config_file_path, DEFAULT_SETTINGS)?;
+    }
+
+    // Environment and command-line overrides are applied on top of the file,
+    // never saved to it.
+    let config = source.load()?;
+    let mut lure = Lure::new(config)?;
+    lure.start(source).await?;
+    Ok(())
+}
+
+fn check(source: &ConfigSource) -> anyhow::Result<()> {
+    validation::validate(&source.load()?).check()?;
+    info!("Config {} is valid.", source.path);
+    Ok(())
+}
+
+fn init(path: &str, force: bool) -> anyhow::Result<()> {
+    if !force && Path::new(path).exists() {
+        bail!("config {path} already exists, use --force to overwrite it");
+    }
+
+    fs::write(path, DEFAULT_SETTINGS)?;
+    info!("Wrote default config to {path}.");
+    Ok(())
+}
+
+fn fill(path: &str) -> anyhow::Result<()> {
+    let added = LureConfig::fill_missing(path)?;
+    for key in &added {
+        info!("Added missing key {key} to {path}.");
+    }
+    if added.is_empty() {
+        info!("Config {path} has no missing keys.");
+    }
     Ok(())
 }
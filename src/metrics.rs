@@ -0,0 +1,52 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Monotonic event counter.
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Proxy-wide counters, logged periodically while they keep changing.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub connections: Counter,
+    pub denied_connections: Counter,
+    pub throttled_connections: Counter,
+    pub rejected_handshakes: Counter,
+    last_report: Mutex<String>,
+}
+
+impl Metrics {
+    /// Logs the counters if any changed since the last report.
+    pub fn report(&self) {
+        let report = self.to_string();
+        let mut last_report = self.last_report.lock().unwrap();
+        if *last_report != report {
+            println!("Metrics: {report}");
+            *last_report = report;
+        }
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "connections={} denied_connections={} throttled_connections={} rejected_handshakes={}",
+            self.connections.get(),
+            self.denied_connections.get(),
+            self.throttled_connections.get(),
+            self.rejected_handshakes.get()
+        )
+    }
+}
@@ -0,0 +1,47 @@
+//! Serverbound play packet names of the releases Lure speaks, so settings
+//! can refer to packets by name rather than by ID. Every release lists its
+//! packets in ID order.
+
+mod v1_19_3;
+
+/// Serverbound play packets of a single release.
+pub struct Release {
+    pub protocol_version: i32,
+    pub serverbound: &'static [&'static str],
+}
+
+/// Releases whose packets Lure knows, oldest first.
+pub static RELEASES: &[&Release] = &[&v1_19_3::RELEASE];
+
+/// ID of a serverbound packet by name in the given protocol version, if Lure
+/// knows the release.
+pub fn packet_id(protocol_version: i32, name: &str) -> Option<i32> {
+    let release = RELEASES
+        .iter()
+        .find(|release| release.protocol_version == protocol_version)?;
+    let id = release.serverbound.iter().position(|n| *n == name)?;
+    Some(id as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_release_lists() {
+        for release in RELEASES {
+            let packets = release.serverbound;
+            for (i, name) in packets.iter().enumerate() {
+                assert!(
+                    !packets[i + 1..].contains(name),
+                    "{} lists {name} twice",
+                    release.protocol_version
+                );
+            }
+        }
+
+        assert_eq!(packet_id(761, "chat_message"), Some(0x05));
+        assert_eq!(packet_id(761, "use_item"), Some(0x32));
+        assert_eq!(packet_id(47, "chat_message"), None);
+    }
+}
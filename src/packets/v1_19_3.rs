@@ -0,0 +1,60 @@
+use super::Release;
+
+pub static RELEASE: Release = Release {
+    protocol_version: 761,
+    serverbound: SERVERBOUND,
+};
+
+pub const SERVERBOUND: &[&str] = &[
+    "confirm_teleportation",
+    "query_block_entity_tag",
+    "change_difficulty",
+    "message_acknowledgment",
+    "chat_command",
+    "chat_message",
+    "client_command",
+    "client_information",
+    "command_suggestions_request",
+    "click_container_button",
+    "click_container",
+    "close_container",
+    "plugin_message",
+    "edit_book",
+    "query_entity_tag",
+    "interact",
+    "jigsaw_generate",
+    "keep_alive",
+    "lock_difficulty",
+    "set_player_position",
+    "set_player_position_and_rotation",
+    "set_player_rotation",
+    "set_player_on_ground",
+    "move_vehicle",
+    "paddle_boat",
+    "pick_item",
+    "place_recipe",
+    "player_abilities",
+    "player_action",
+    "player_command",
+    "player_input",
+    "pong",
+    "player_session",
+    "change_recipe_book_settings",
+    "set_seen_recipe",
+    "rename_item",
+    "resource_pack",
+    "seen_advancements",
+    "select_trade",
+    "set_beacon_effect",
+    "set_held_item",
+    "program_command_block",
+    "program_command_block_minecart",
+    "set_creative_mode_slot",
+    "program_jigsaw_block",
+    "program_structure_block",
+    "update_sign",
+    "swing_arm",
+    "teleport_to_entity",
+    "use_item_on",
+    "use_item",
+];
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Players currently connected through the proxy.
+#[derive(Debug, Default)]
+pub struct PlayerRegistry {
+    /// Online name and session count, keyed by lowercase username.
+    inner: Mutex<HashMap<String, (String, u32)>>,
+}
+
+/// Keeps a player registered until dropped.
+#[derive(Debug)]
+pub struct PlayerGuard {
+    players: Arc<PlayerRegistry>,
+    key: String,
+}
+
+impl Drop for PlayerGuard {
+    fn drop(&mut self) {
+        let mut lck = self.players.inner.lock().unwrap();
+        if let Some((_, sessions)) = lck.get_mut(&self.key) {
+            *sessions -= 1;
+            if *sessions == 0 {
+                lck.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl PlayerRegistry {
+    /// Registers a player, failing if `deny_case_collisions` is set and an
+    /// online player's name equals `username` ignoring case, but not exactly.
+    pub fn register(
+        self: &Arc<Self>,
+        username: &str,
+        deny_case_collisions: bool,
+    ) -> Option<PlayerGuard> {
+        let key = username.to_lowercase();
+        let mut lck = self.inner.lock().unwrap();
+        let (online, sessions) = lck
+            .entry(key.to_owned())
+            .or_insert_with(|| (username.to_owned(), 0));
+
+        if deny_case_collisions && online != username {
+            return None;
+        }
+        *sessions += 1;
+
+        Some(PlayerGuard {
+            players: self.clone(),
+            key,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn players_case_collisions() {
+        let players = Arc::new(PlayerRegistry::default());
+        let guard = players.register("Sammwy", true).unwrap();
+
+        assert!(players.register("sammwy", true).is_none());
+        assert!(players.register("Sammwy", true).is_some());
+        assert!(players.register("Other", true).is_some());
+        assert!(players.register("sammwy", false).is_some());
+
+        drop(guard);
+        assert!(players.register("sammwy", true).is_some());
+    }
+}
@@ -0,0 +1,128 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use anyhow::{bail, ensure, Context};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Signature every version 2 header starts with.
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Longest version 1 header, including the trailing CRLF.
+const V1_MAX_LENGTH: usize = 107;
+
+/// Longest version 2 address block accepted, leaving room for TLVs.
+const V2_MAX_LENGTH: usize = 1024;
+
+/// Reads a PROXY protocol header, version 1 or 2, from the start of a
+/// connection. Returns the client address it carries, or `None` for health
+/// checks and other connections made by the proxy itself.
+pub async fn read_header<R: AsyncRead + Unpin>(
+    stream: &mut R,
+) -> anyhow::Result<Option<SocketAddr>> {
+    let mut start = [0u8; 6];
+    stream.read_exact(&mut start).await?;
+
+    if &start == b"PROXY " {
+        let mut line = start.to_vec();
+        while !line.ends_with(b"\r\n") {
+            ensure!(line.len() < V1_MAX_LENGTH, "PROXY header too long");
+            line.push(stream.read_u8().await?);
+        }
+        let line = std::str::from_utf8(&line).context("PROXY header is not ASCII")?;
+        return parse_v1(line);
+    }
+
+    ensure!(start == V2_SIGNATURE[..6], "missing PROXY header");
+
+    let mut header = [0u8; 16];
+    header[..6].copy_from_slice(&start);
+    stream.read_exact(&mut header[6..]).await?;
+    ensure!(header[..12] == V2_SIGNATURE, "missing PROXY header");
+
+    let length = u16::from_be_bytes([header[14], header[15]]) as usize;
+    ensure!(length <= V2_MAX_LENGTH, "PROXY header too long");
+    let mut addresses = vec![0u8; length];
+    stream.read_exact(&mut addresses).await?;
+
+    parse_v2(header[12], header[13], &addresses)
+}
+
+/// Parses a line such as `PROXY TCP4 203.0.113.7 198.51.100.1 51234 25565\r\n`.
+fn parse_v1(line: &str) -> anyhow::Result<Option<SocketAddr>> {
+    let fields: Vec<&str> = line.trim_end_matches("\r\n").split(' ').collect();
+
+    match fields[..] {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", "TCP4" | "TCP6", source, _, port, _] => {
+            let ip: IpAddr = source.parse().context("invalid PROXY source address")?;
+            let port: u16 = port.parse().context("invalid PROXY source port")?;
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+        _ => bail!("malformed PROXY header"),
+    }
+}
+
+fn parse_v2(
+    version_command: u8,
+    family: u8,
+    addresses: &[u8],
+) -> anyhow::Result<Option<SocketAddr>> {
+    ensure!(version_command >> 4 == 2, "unsupported PROXY protocol version");
+
+    match version_command & 0x0F {
+        // LOCAL, sent by the proxy itself.
+        0x0 => return Ok(None),
+        // PROXY
+        0x1 => {}
+        command => bail!("unknown PROXY command {command}"),
+    }
+
+    let port = |at: usize| u16::from_be_bytes([addresses[at], addresses[at + 1]]);
+
+    match family >> 4 {
+        // AF_INET
+        0x1 => {
+            ensure!(addresses.len() >= 12, "truncated PROXY addresses");
+            let ip: [u8; 4] = addresses[..4].try_into()?;
+            Ok(Some(SocketAddr::new(Ipv4Addr::from(ip).into(), port(8))))
+        }
+        // AF_INET6
+        0x2 => {
+            ensure!(addresses.len() >= 36, "truncated PROXY addresses");
+            let ip: [u8; 16] = addresses[..16].try_into()?;
+            Ok(Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port(32))))
+        }
+        // AF_UNSPEC and AF_UNIX carry no client address.
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn proxy_protocol_headers() {
+        let mut v1: &[u8] = b"PROXY TCP4 203.0.113.7 198.51.100.1 51234 25565\r\n\x10";
+        assert_eq!(
+            read_header(&mut v1).await.unwrap(),
+            Some("203.0.113.7:51234".parse().unwrap())
+        );
+        assert_eq!(v1, b"\x10");
+
+        let mut v2 = V2_SIGNATURE.to_vec();
+        v2.extend([0x21, 0x11, 0, 12]);
+        v2.extend([203, 0, 113, 7, 198, 51, 100, 1, 0xC8, 0x22, 0x63, 0xDD, 0x10]);
+        let mut v2 = &v2[..];
+        assert_eq!(
+            read_header(&mut v2).await.unwrap(),
+            Some("203.0.113.7:51234".parse().unwrap())
+        );
+        assert_eq!(v2, b"\x10");
+
+        let mut local: &[u8] = b"PROXY UNKNOWN\r\n";
+        assert_eq!(read_header(&mut local).await.unwrap(), None);
+
+        let mut handshake: &[u8] = b"\x10\x00\xf9\x05\x09localhost";
+        assert!(read_header(&mut handshake).await.is_err());
+    }
+}
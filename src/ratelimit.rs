@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use anyhow::bail;
+
+use crate::config::{RateBudget, RateLimitConfig};
+use crate::packets;
+
+/// Token bucket refilled at a steady rate, up to its burst size.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(budget: &RateBudget, now: Instant) -> Self {
+        let capacity = budget.burst.max(budget.per_second) as f64;
+        Self {
+            rate: budget.per_second as f64,
+            capacity,
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    fn take(&mut self, amount: f64, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+
+        if self.tokens < amount {
+            return false;
+        }
+
+        self.tokens -= amount;
+        true
+    }
+}
+
+/// Packet and byte budgets of a single player's serverbound traffic.
+#[derive(Debug)]
+pub struct PacketRateLimiter {
+    packets: TokenBucket,
+    bytes: TokenBucket,
+    /// Budgets of specific packets keyed by packet ID.
+    overrides: HashMap<i32, (String, TokenBucket)>,
+}
+
+impl PacketRateLimiter {
+    /// Builds the budgets for a client, resolving packet names in the
+    /// overrides to the IDs of its protocol version. Unknown names are
+    /// skipped, validation warns about them once.
+    pub fn new(config: &RateLimitConfig, protocol_version: i32) -> Self {
+        let now = Instant::now();
+        let mut overrides = HashMap::new();
+
+        for (name, budget) in &config.overrides {
+            if let Some(id) = packets::packet_id(protocol_version, name) {
+                overrides.insert(id, (name.to_owned(), TokenBucket::new(budget, now)));
+            }
+        }
+
+        Self {
+            packets: TokenBucket::new(&config.packets, now),
+            bytes: TokenBucket::new(&config.bytes, now),
+            overrides,
+        }
+    }
+
+    /// Counts a packet against the budgets, failing if any is exhausted.
+    pub fn check(&mut self, id: i32, size: usize) -> anyhow::Result<()> {
+        let now = Instant::now();
+
+        if let Some((name, bucket)) = self.overrides.get_mut(&id) {
+            if !bucket.take(1.0, now) {
+                bail!("exceeded the {name} packet rate limit");
+            }
+        }
+
+        if !self.packets.take(1.0, now) {
+            bail!("exceeded the packet rate limit");
+        }
+
+        if !self.bytes.take(size as f64, now) {
+            bail!("exceeded the byte rate limit");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratelimit_budgets() {
+        let config = RateLimitConfig {
+            packets: RateBudget {
+                per_second: 1,
+                burst: 3,
+            },
+            overrides: HashMap::from([(
+                "chat_message".to_string(),
+                RateBudget {
+                    per_second: 1,
+                    burst: 1,
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let chat_message = packets::packet_id(761, "chat_message").unwrap();
+        let mut limiter = PacketRateLimiter::new(&config, 761);
+
+        assert!(limiter.check(chat_message, 10).is_ok());
+        assert!(limiter.check(chat_message, 10).is_err());
+
+        let mut limiter = PacketRateLimiter::new(&config, 761);
+        for _ in 0..3 {
+            assert!(limiter.check(0x00, 10).is_ok());
+        }
+        assert!(limiter.check(0x00, 10).is_err());
+    }
+}
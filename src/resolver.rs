@@ -0,0 +1,149 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::{ensure, Context};
+use tracing::warn;
+use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::config::{BackendAddress, DEFAULT_PORT};
+
+/// Resolves backend addresses, keeping each result for as long as the DNS
+/// records it came from are valid.
+pub struct BackendResolver {
+    resolver: TokioAsyncResolver,
+    cache: Mutex<HashMap<BackendAddress, (Vec<SocketAddr>, Instant)>>,
+}
+
+impl BackendResolver {
+    pub fn new() -> anyhow::Result<Self> {
+        let resolver = match TokioAsyncResolver::tokio_from_system_conf() {
+            Ok(resolver) => resolver,
+            Err(e) => {
+                warn!("Failed to read the system DNS config, using public resolvers: {e}");
+                TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default())?
+            }
+        };
+
+        Ok(Self {
+            resolver,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Addresses to try, in order, to reach a backend.
+    pub async fn resolve(&self, address: &BackendAddress) -> anyhow::Result<Vec<SocketAddr>> {
+        let (host, port) = match address {
+            BackendAddress::Socket(address) => return Ok(vec![*address]),
+            BackendAddress::Host(host, port) => (host, Some(*port)),
+            BackendAddress::Srv(host) => (host, None),
+        };
+
+        if let Some(addresses) = self.cached(address) {
+            return Ok(addresses);
+        }
+
+        let (addresses, valid_until) = match port {
+            Some(port) => self.lookup(host, port).await?,
+            None => self.lookup_srv(host).await?,
+        };
+        ensure!(!addresses.is_empty(), "{address} has no addresses");
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, (_, valid_until)| *valid_until > Instant::now());
+        cache.insert(address.to_owned(), (addresses.clone(), valid_until));
+        Ok(addresses)
+    }
+
+    fn cached(&self, address: &BackendAddress) -> Option<Vec<SocketAddr>> {
+        let cache = self.cache.lock().unwrap();
+        let (addresses, valid_until) = cache.get(address)?;
+        (*valid_until > Instant::now()).then(|| addresses.clone())
+    }
+
+    async fn lookup(&self, host: &str, port: u16) -> anyhow::Result<(Vec<SocketAddr>, Instant)> {
+        let ips = self
+            .resolver
+            .lookup_ip(host)
+            .await
+            .with_context(|| format!("failed to resolve {host}"))?;
+        let addresses = ips.iter().map(|ip| SocketAddr::new(ip, port)).collect();
+        Ok((addresses, ips.valid_until()))
+    }
+
+    /// Looks up the `_minecraft._tcp` SRV records of `host`, falling back to
+    /// the host itself on the default port if it has none. Targets are tried
+    /// by priority, then heaviest weight first.
+    async fn lookup_srv(&self, host: &str) -> anyhow::Result<(Vec<SocketAddr>, Instant)> {
+        let records = match self
+            .resolver
+            .srv_lookup(format!("_minecraft._tcp.{host}"))
+            .await
+        {
+            Ok(records) => records,
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                return self.lookup(host, DEFAULT_PORT).await;
+            }
+            Err(e) => return Err(e).with_context(|| format!("failed to resolve {host}")),
+        };
+
+        let mut targets: Vec<_> = records.iter().collect();
+        targets.sort_by_key(|srv| (srv.priority(), Reverse(srv.weight())));
+
+        let mut addresses = Vec::new();
+        let mut valid_until = records.as_lookup().valid_until();
+        for srv in targets {
+            let target = srv.target().to_utf8();
+            match self.lookup(&target, srv.port()).await {
+                Ok((found, until)) => {
+                    addresses.extend(found);
+                    valid_until = valid_until.min(until);
+                }
+                Err(e) => warn!("Skipping SRV target of {host}: {e:#}"),
+            }
+        }
+
+        Ok((addresses, valid_until))
+    }
+}
+
+impl fmt::Debug for BackendResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackendResolver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn resolver_cache_expires() {
+        let resolver = BackendResolver::new().unwrap();
+        let socket: SocketAddr = "10.0.0.1:25565".parse().unwrap();
+        let resolved = resolver.resolve(&socket.into()).await.unwrap();
+        assert_eq!(resolved, [socket]);
+
+        let address = BackendAddress::Srv("play.example.com".to_string());
+        let now = Instant::now();
+        let mut cache = resolver.cache.lock().unwrap();
+        cache.insert(
+            address.clone(),
+            (vec![socket], now + Duration::from_secs(60)),
+        );
+        drop(cache);
+        assert_eq!(resolver.cached(&address), Some(vec![socket]));
+
+        let mut cache = resolver.cache.lock().unwrap();
+        cache.insert(address.clone(), (vec![socket], now));
+        drop(cache);
+        assert_eq!(resolver.cached(&address), None);
+    }
+}
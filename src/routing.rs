@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{ensure, Context};
+use regex::{Regex, RegexBuilder};
+
+/// Key of the route used when no other one matches.
+pub const FALLBACK_HOST: &str = "*";
+
+/// Prefix marking a key of `hosts` as a regular expression.
+pub const REGEX_PREFIX: char = '~';
+
+/// Hostnames a key of `hosts` matches.
+#[derive(Debug)]
+pub enum HostPattern {
+    /// The hostname itself.
+    Exact(String),
+    /// Any subdomain of a domain, written as `*.example.com`. Holds the
+    /// suffix including its leading dot.
+    Suffix(String),
+    /// A regular expression matching the whole hostname, written as
+    /// `~(?<srv>\w+)\.example\.com`. The server may refer to its capture
+    /// groups, e.g. `$srv`.
+    Regex(Regex),
+    /// Any hostname, written as `*`.
+    Any,
+}
+
+impl HostPattern {
+    pub fn parse(key: &str) -> anyhow::Result<Self> {
+        if key == FALLBACK_HOST {
+            return Ok(Self::Any);
+        }
+
+        if let Some(pattern) = key.strip_prefix(REGEX_PREFIX) {
+            let regex = RegexBuilder::new(&format!("^(?:{pattern})$"))
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("invalid host pattern '{pattern}'"))?;
+            return Ok(Self::Regex(regex));
+        }
+
+        let key = key.to_ascii_lowercase();
+        match key.strip_prefix('*') {
+            Some(suffix) => {
+                ensure!(
+                    suffix.starts_with('.') && suffix.len() > 1 && !suffix.contains('*'),
+                    "invalid wildcard host '{key}', expected *.domain"
+                );
+                Ok(Self::Suffix(suffix.to_owned()))
+            }
+            None => {
+                ensure!(
+                    !key.contains('*'),
+                    "wildcards are only allowed as '*.domain'"
+                );
+                Ok(Self::Exact(key))
+            }
+        }
+    }
+
+    /// Whether the pattern matches a lowercase hostname.
+    pub fn matches(&self, hostname: &str) -> bool {
+        match self {
+            Self::Exact(exact) => hostname == exact,
+            Self::Suffix(suffix) => hostname.ends_with(suffix.as_str()),
+            Self::Regex(regex) => regex.is_match(hostname),
+            Self::Any => true,
+        }
+    }
+}
+
+/// Server chosen for a hostname and the `hosts` key that chose it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub rule: String,
+    pub server: String,
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (hosts.\"{}\")",
+            self.server,
+            self.rule.escape_debug()
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    key: String,
+    server: String,
+}
+
+/// Picks the server for a hostname from the `hosts` table. Matching is case
+/// insensitive and tries, in order: the exact hostname, the longest matching
+/// `*.domain` wildcard, regular expressions sorted by key, and finally `*`.
+#[derive(Debug, Default)]
+pub struct HostRouter {
+    exact: HashMap<String, Rule>,
+    suffixes: Vec<(String, Rule)>,
+    regexes: Vec<(Regex, Rule)>,
+    fallback: Option<Rule>,
+}
+
+impl HostRouter {
+    pub fn new(hosts: &HashMap<String, String>) -> anyhow::Result<Self> {
+        let mut router = Self::default();
+
+        let mut keys: Vec<&String> = hosts.keys().collect();
+        keys.sort();
+
+        for key in keys {
+            let rule = Rule {
+                key: key.to_owned(),
+                server: hosts[key].to_owned(),
+            };
+
+            match HostPattern::parse(key)? {
+                HostPattern::Exact(hostname) => {
+                    router.exact.insert(hostname, rule);
+                }
+                HostPattern::Suffix(suffix) => router.suffixes.push((suffix, rule)),
+                HostPattern::Regex(regex) => router.regexes.push((regex, rule)),
+                HostPattern::Any => router.fallback = Some(rule),
+            }
+        }
+
+        router
+            .suffixes
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+        Ok(router)
+    }
+
+    pub fn route(&self, hostname: &str) -> Option<Route> {
+        let hostname = hostname.to_ascii_lowercase();
+
+        if let Some(rule) = self.exact.get(&hostname) {
+            return Some(Self::matched(rule, rule.server.to_owned()));
+        }
+
+        if let Some((_, rule)) = self
+            .suffixes
+            .iter()
+            .find(|(suffix, _)| hostname.ends_with(suffix.as_str()))
+        {
+            return Some(Self::matched(rule, rule.server.to_owned()));
+        }
+
+        for (regex, rule) in &self.regexes {
+            if let Some(captures) = regex.captures(&hostname) {
+                let mut server = String::new();
+                captures.expand(&rule.server, &mut server);
+                return Some(Self::matched(rule, server));
+            }
+        }
+
+        self.fallback
+            .as_ref()
+            .map(|rule| Self::matched(rule, rule.server.to_owned()))
+    }
+
+    fn matched(rule: &Rule, server: String) -> Route {
+        Route {
+            rule: rule.key.to_owned(),
+            server,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routing_precedence() {
+        let hosts = HashMap::from(
+            [
+                ("*", "lobby"),
+                ("Play.Example.com", "survival"),
+                ("*.example.com", "hub"),
+                ("*.eu.example.com", "hub-eu"),
+                (r"~(?<srv>\w+)\.mc\.example\.com", "$srv"),
+                (r"~(?<srv>\w+)\.mc\.example\.net", "mc-$srv"),
+            ]
+            .map(|(key, server)| (key.to_string(), server.to_string())),
+        );
+        let router = HostRouter::new(&hosts).unwrap();
+        let server = |hostname| router.route(hostname).unwrap().server;
+
+        assert_eq!(server("play.example.com"), "survival");
+        assert_eq!(server("PLAY.example.com"), "survival");
+        assert_eq!(server("a.example.com"), "hub");
+        assert_eq!(server("a.eu.example.com"), "hub-eu");
+        assert_eq!(server("creative.mc.example.com"), "hub");
+        assert_eq!(server("creative.mc.example.net"), "mc-creative");
+        assert_eq!(server("example.com"), "lobby");
+        assert_eq!(router.route("a.example.com").unwrap().rule, "*.example.com");
+
+        assert!(HostPattern::parse("play.*.com").is_err());
+        assert!(HostPattern::parse("~(").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use serde_json::json;
+
+use valence::prelude::*;
+
+use crate::config::{ProxyConfig, ServerConfig};
+use crate::utils::{file_modified, read_favicons};
+
+/// Status response shared by every ping, rebuilt only when the favicon file
+/// or directory changes on disk.
+#[derive(Debug)]
+pub struct StatusCache {
+    proxy: ProxyConfig,
+    /// Fields of servers overriding the motd, keyed by server name.
+    server_fields: HashMap<String, String>,
+    favicon_path: PathBuf,
+    next_favicon: AtomicUsize,
+    inner: RwLock<CachedStatus>,
+}
+
+#[derive(Debug)]
+struct CachedStatus {
+    favicon_modified: Option<SystemTime>,
+    /// Every field between `version` and `favicon`, serialized without the
+    /// surrounding braces.
+    fields: String,
+    /// Encoded favicons, served in rotation.
+    favicons: Vec<String>,
+}
+
+impl StatusCache {
+    pub fn new(proxy: &ProxyConfig, servers: &HashMap<String, ServerConfig>) -> Self {
+        let favicon_path = PathBuf::from(&proxy.favicon);
+        let server_fields = servers
+            .iter()
+            .filter_map(|(name, server)| {
+                let motd = server.motd.as_ref()?;
+                Some((name.to_owned(), Self::build_fields(proxy, motd)))
+            })
+            .collect();
+
+        Self {
+            proxy: proxy.to_owned(),
+            server_fields,
+            next_favicon: AtomicUsize::new(0),
+            inner: RwLock::new(CachedStatus {
+                favicon_modified: Self::modified(&favicon_path),
+                fields: Self::build_fields(proxy, &proxy.motd),
+                favicons: read_favicons(&favicon_path),
+            }),
+            favicon_path,
+        }
+    }
+
+    /// Reloads the favicons if any of them was modified, created or removed
+    /// since the last check.
+    pub fn refresh(&self) {
+        let favicon_modified = Self::modified(&self.favicon_path);
+        if self.inner.read().unwrap().favicon_modified == favicon_modified {
+            return;
+        }
+
+        println!("Favicon {} changed, reloading.", self.proxy.favicon);
+        let favicons = read_favicons(&self.favicon_path);
+
+        let mut lck = self.inner.write().unwrap();
+        lck.favicon_modified = favicon_modified;
+        lck.favicons = favicons;
+    }
+
+    /// Returns the status JSON reporting the given version, with the motd of
+    /// `server` if it has its own. Clients show the version name in red when
+    /// the protocol doesn't match their own.
+    pub fn response_json(&self, name: &str, protocol: i32, server: Option<&str>) -> String {
+        let lck = self.inner.read().unwrap();
+        let fields = server
+            .and_then(|server| self.server_fields.get(server))
+            .unwrap_or(&lck.fields);
+        let mut json = format!(
+            "{{\"version\":{},{}",
+            json!({ "name": name, "protocol": protocol }),
+            fields
+        );
+
+        if !lck.favicons.is_empty() {
+            let next = self.next_favicon.fetch_add(1, Ordering::Relaxed);
+            json.push_str(",\"favicon\":\"");
+            json.push_str(&lck.favicons[next % lck.favicons.len()]);
+            json.push('"');
+        }
+
+        json.push('}');
+        json
+    }
+
+    /// Latest modification time of the favicon, or of the directory and any
+    /// file inside it.
+    fn modified(path: &Path) -> Option<SystemTime> {
+        let modified = file_modified(path);
+        if !path.is_dir() {
+            return modified;
+        }
+
+        fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+            .chain(modified)
+            .max()
+    }
+
+    fn build_fields(proxy: &ProxyConfig, motd: &str) -> String {
+        let motd: Text = motd.to_owned().into();
+
+        let json = json!({
+            "players": {
+                "online": 0,
+                "max": proxy.max_players,
+                "sample": vec![PlayerSampleEntry {
+                    name: "foobar".into(),
+                    id: Uuid::from_u128(12345),
+                }],
+            },
+            "description": motd,
+        });
+
+        let json = json.to_string();
+        json[1..json.len() - 1].to_string()
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::ThrottleConfig;
+
+/// Per-IP limits on concurrent connections and on how many connections,
+/// status pings and logins may be started within a time window.
+#[derive(Debug)]
+pub struct ConnectionThrottle {
+    config: ThrottleConfig,
+    inner: Mutex<HashMap<IpAddr, IpState>>,
+}
+
+#[derive(Debug)]
+struct IpState {
+    active: u32,
+    window_start: Instant,
+    connections: u32,
+    status: u32,
+    logins: u32,
+}
+
+impl IpState {
+    fn new(now: Instant) -> Self {
+        Self {
+            active: 0,
+            window_start: now,
+            connections: 0,
+            status: 0,
+            logins: 0,
+        }
+    }
+}
+
+/// Counts as an active connection of its IP until dropped.
+#[derive(Debug)]
+pub struct ThrottleGuard {
+    throttle: Arc<ConnectionThrottle>,
+    ip: IpAddr,
+}
+
+impl Drop for ThrottleGuard {
+    fn drop(&mut self) {
+        let mut lck = self.throttle.inner.lock().unwrap();
+        if let Some(state) = lck.get_mut(&self.ip) {
+            state.active -= 1;
+        }
+    }
+}
+
+/// Kind of request counted against its own limit once the handshake is read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThrottleKind {
+    Status,
+    Login,
+}
+
+impl ConnectionThrottle {
+    pub fn new(config: &ThrottleConfig) -> Self {
+        Self {
+            config: config.to_owned(),
+            inner: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_millis(self.config.window_ms)
+    }
+
+    /// Registers a new connection, or returns `None` if the IP is over its
+    /// concurrent or per-window connection limit.
+    pub fn try_accept(self: &Arc<Self>, ip: IpAddr) -> Option<ThrottleGuard> {
+        let now = Instant::now();
+        let window = self.window();
+        let mut lck = self.inner.lock().unwrap();
+        let state = lck.entry(ip).or_insert_with(|| IpState::new(now));
+
+        if now.duration_since(state.window_start) >= window {
+            *state = IpState {
+                active: state.active,
+                ..IpState::new(now)
+            };
+        }
+
+        if exceeds(state.active, self.config.max_connections_per_ip)
+            || exceeds(state.connections, self.config.connections_per_window)
+        {
+            return None;
+        }
+
+        state.active += 1;
+        state.connections += 1;
+
+        Some(ThrottleGuard {
+            throttle: self.clone(),
+            ip,
+        })
+    }
+
+    /// Counts a status ping or login, returning `false` if the IP is over the
+    /// limit for that kind in the current window.
+    pub fn allow(&self, ip: IpAddr, kind: ThrottleKind) -> bool {
+        let mut lck = self.inner.lock().unwrap();
+        let state = match lck.get_mut(&ip) {
+            Some(state) => state,
+            None => return true,
+        };
+
+        let (count, limit) = match kind {
+            ThrottleKind::Status => (&mut state.status, self.config.status_per_window),
+            ThrottleKind::Login => (&mut state.logins, self.config.logins_per_window),
+        };
+
+        if exceeds(*count, limit) {
+            return false;
+        }
+
+        *count += 1;
+        true
+    }
+
+    /// Forgets IPs without active connections whose window has ended.
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        let window = self.window();
+        self.inner.lock().unwrap().retain(|_, state| {
+            state.active > 0 || now.duration_since(state.window_start) < window
+        });
+    }
+}
+
+/// Whether `count` already reached `limit`, where a limit of 0 is unlimited.
+fn exceeds(count: u32, limit: u32) -> bool {
+    limit != 0 && count >= limit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle() -> Arc<ConnectionThrottle> {
+        Arc::new(ConnectionThrottle::new(&ThrottleConfig {
+            max_connections_per_ip: 2,
+            window_ms: 60_000,
+            connections_per_window: 3,
+            status_per_window: 1,
+            logins_per_window: 1,
+            message: String::new(),
+        }))
+    }
+
+    #[test]
+    fn throttle_concurrent_and_window_limits() {
+        let throttle = throttle();
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let first = throttle.try_accept(ip).unwrap();
+        let _second = throttle.try_accept(ip).unwrap();
+        assert!(throttle.try_accept(ip).is_none());
+        assert!(throttle.try_accept(other).is_some());
+
+        drop(first);
+        let third = throttle.try_accept(ip).unwrap();
+
+        drop(third);
+        assert!(throttle.try_accept(ip).is_none());
+    }
+
+    #[test]
+    fn throttle_status_and_logins_counted_separately() {
+        let throttle = throttle();
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let _guard = throttle.try_accept(ip).unwrap();
+
+        assert!(throttle.allow(ip, ThrottleKind::Status));
+        assert!(!throttle.allow(ip, ThrottleKind::Status));
+        assert!(throttle.allow(ip, ThrottleKind::Login));
+        assert!(!throttle.allow(ip, ThrottleKind::Login));
+    }
+}
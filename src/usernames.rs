@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use regex::Regex;
+
+use crate::config::UsernameConfig;
+
+/// Rule a username broke, each with its own disconnect message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UsernameViolation {
+    Length,
+    Characters,
+    Pattern,
+    Blocked,
+}
+
+/// Checks usernames against the configured policy before the player is
+/// authenticated.
+#[derive(Debug)]
+pub struct UsernamePolicy {
+    config: UsernameConfig,
+    pattern: Option<Regex>,
+    /// Lowercase blocked names.
+    blocked: HashSet<String>,
+}
+
+impl UsernamePolicy {
+    pub fn new(config: &UsernameConfig) -> anyhow::Result<Self> {
+        let pattern = match config.pattern.as_str() {
+            "" => None,
+            pattern => Some(
+                Regex::new(pattern)
+                    .with_context(|| format!("invalid username pattern '{pattern}'"))?,
+            ),
+        };
+
+        Ok(Self {
+            config: config.to_owned(),
+            pattern,
+            blocked: config
+                .blocked
+                .iter()
+                .map(|name| name.to_lowercase())
+                .collect(),
+        })
+    }
+
+    pub fn check(&self, username: &str) -> Result<(), UsernameViolation> {
+        let length = username.chars().count();
+        if length < self.config.min_length || length > self.config.max_length {
+            return Err(UsernameViolation::Length);
+        }
+
+        let allowed = &self.config.allowed_characters;
+        if !allowed.is_empty() && !username.chars().all(|c| allowed.contains(c)) {
+            return Err(UsernameViolation::Characters);
+        }
+
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(username) {
+                return Err(UsernameViolation::Pattern);
+            }
+        }
+
+        if self.blocked.contains(&username.to_lowercase()) {
+            return Err(UsernameViolation::Blocked);
+        }
+
+        Ok(())
+    }
+
+    /// Disconnect message for a violation.
+    pub fn message(&self, violation: UsernameViolation) -> String {
+        match violation {
+            UsernameViolation::Length => self
+                .config
+                .length_message
+                .replace("{min}", &self.config.min_length.to_string())
+                .replace("{max}", &self.config.max_length.to_string()),
+            UsernameViolation::Characters => self.config.characters_message.to_owned(),
+            UsernameViolation::Pattern => self.config.pattern_message.to_owned(),
+            UsernameViolation::Blocked => self.config.blocked_message.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usernames_policy_violations() {
+        let policy = UsernamePolicy::new(&UsernameConfig {
+            pattern: "^[a-z]".to_string(),
+            blocked: vec!["Admin".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(policy.check("sammwy"), Ok(()));
+        assert_eq!(policy.check("ab"), Err(UsernameViolation::Length));
+        assert_eq!(policy.check("sam-wy"), Err(UsernameViolation::Characters));
+        assert_eq!(policy.check("Sammwy"), Err(UsernameViolation::Pattern));
+        assert_eq!(policy.check("admin"), Err(UsernameViolation::Blocked));
+    }
+
+    #[test]
+    fn usernames_invalid_pattern() {
+        assert!(UsernamePolicy::new(&UsernameConfig {
+            pattern: "(".to_string(),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -1,30 +1,156 @@
-use std::{fs, path::PathBuf};
+use std::fs;
+use std::io::Cursor;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
 
 use base64::{engine::general_purpose, Engine};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat, RgbaImage};
+use ipnet::IpNet;
 
-pub fn read_favicon(path: String) -> Option<String> {
-    let favicon_file = PathBuf::from(path);
+/// Width and height the client expects for server icons.
+const FAVICON_SIZE: u32 = 64;
 
-    if !favicon_file.exists() {
-        println!(
-            "doesnt exist {:?}",
-            favicon_file.as_os_str().to_str()
+/// Last modification time of a file, `None` if it doesn't exist.
+pub fn file_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Key as written in a TOML path, quoted unless it's a bare key.
+pub fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+    match bare {
+        true => key.to_owned(),
+        false => format!("{key:?}"),
+    }
+}
+
+/// Parses a CIDR range, or a single address as a range of one.
+pub fn parse_ip_net(value: &str) -> Option<IpNet> {
+    IpNet::from_str(value)
+        .ok()
+        .or_else(|| IpAddr::from_str(value).ok().map(IpNet::from))
+}
+
+/// Loads the favicon at `path`, or every image inside it when `path` is a
+/// directory so they can be served in rotation.
+pub fn read_favicons(path: &Path) -> Vec<String> {
+    if !path.is_dir() {
+        return read_favicon(path).into_iter().collect();
+    }
+
+    let mut files: Vec<PathBuf> = match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|file| file.is_file())
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to read favicon directory {}: {e}", path.display());
+            return vec![];
+        }
+    };
+    files.sort();
+
+    let favicons: Vec<String> = files.iter().filter_map(|file| read_favicon(file)).collect();
+    if favicons.is_empty() {
+        eprintln!(
+            "Favicon directory {} contains no usable images, no server icon will be shown.",
+            path.display()
+        );
+    }
+
+    favicons
+}
+
+/// Loads an image in any supported format and encodes it as a 64x64 PNG data
+/// URI, warning about every conversion that was needed.
+pub fn read_favicon(path: &Path) -> Option<String> {
+    if !path.exists() {
+        eprintln!(
+            "Favicon {} does not exist, no server icon will be shown.",
+            path.display()
         );
         return None;
     }
 
-    match fs::read(favicon_file) {
-        Ok(favicon) => {
-            let favicon_meta = image_meta::load_from_buf(&favicon).ok()?;
-            if favicon_meta.dimensions.width != 64 || favicon_meta.dimensions.height != 64 {
-                return None;
-            };
-
-            let mut buf = "data:image/png;base64,".to_string();
-            general_purpose::STANDARD.encode_string(favicon, &mut buf);
-            
-            Some(buf)
-        },
-        Err(_) => None,
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to read favicon {}: {e}", path.display());
+            return None;
+        }
+    };
+
+    let format = match image::guess_format(&bytes) {
+        Ok(format) => format,
+        Err(_) => {
+            eprintln!("Favicon {} is not a supported image format.", path.display());
+            return None;
+        }
+    };
+
+    let favicon = match image::load_from_memory_with_format(&bytes, format) {
+        Ok(favicon) => favicon,
+        Err(e) => {
+            eprintln!("Failed to decode favicon {}: {e}", path.display());
+            return None;
+        }
+    };
+
+    let mut changes = vec![];
+    if format != ImageFormat::Png {
+        changes.push(format!("converted from {format:?} to PNG"));
+    }
+
+    let (width, height) = favicon.dimensions();
+    let png = if width != FAVICON_SIZE || height != FAVICON_SIZE {
+        changes.push(format!(
+            "resized from {width}x{height} to {FAVICON_SIZE}x{FAVICON_SIZE}"
+        ));
+        encode_png(&fit_favicon(&favicon))?
+    } else if format != ImageFormat::Png {
+        encode_png(&favicon)?
+    } else {
+        bytes
+    };
+
+    if !changes.is_empty() {
+        eprintln!("Favicon {} was {}.", path.display(), changes.join(" and "));
     }
+
+    let mut buf = "data:image/png;base64,".to_string();
+    general_purpose::STANDARD.encode_string(png, &mut buf);
+
+    Some(buf)
+}
+
+/// Scales the image down or up to fit the favicon size, centering it on a
+/// transparent background when it isn't square.
+fn fit_favicon(favicon: &DynamicImage) -> DynamicImage {
+    let scaled = favicon.resize(FAVICON_SIZE, FAVICON_SIZE, FilterType::Lanczos3);
+    let x = (FAVICON_SIZE - scaled.width()) / 2;
+    let y = (FAVICON_SIZE - scaled.height()) / 2;
+
+    let mut canvas = RgbaImage::new(FAVICON_SIZE, FAVICON_SIZE);
+    imageops::overlay(&mut canvas, &scaled.to_rgba8(), x as i64, y as i64);
+
+    DynamicImage::ImageRgba8(canvas)
+}
+
+fn encode_png(favicon: &DynamicImage) -> Option<Vec<u8>> {
+    let mut buf = Cursor::new(Vec::new());
+
+    if let Err(e) = favicon.write_to(&mut buf, ImageOutputFormat::Png) {
+        eprintln!("Failed to encode favicon as PNG: {e}");
+        return None;
+    }
+
+    Some(buf.into_inner())
 }
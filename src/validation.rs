@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use anyhow::bail;
+use regex::Regex;
+use tracing::{error, warn};
+use valence_protocol::{MAX_PACKET_SIZE, PROTOCOL_VERSION};
+
+use crate::config::{
+    AntiBotMode, BackendAddress, ForwardingMode, LureConfig, PacketLimits, RateBudget,
+};
+use crate::routing::HostPattern;
+use crate::packets;
+use crate::utils::{parse_ip_net, toml_key};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The proxy runs, but probably not as intended.
+    Warning,
+    /// The proxy refuses to start, or keeps the running config on reload.
+    Error,
+}
+
+/// Problem with a config key, identified by its TOML path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+    /// Included file the key was defined in, if not the config file.
+    pub source: Option<String>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{} in {}: {}", self.path, source, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Every problem found in a config.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    fn push(&mut self, severity: Severity, path: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(Issue {
+            severity,
+            path: path.into(),
+            message: message.into(),
+            source: None,
+        });
+    }
+
+    fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, path, message);
+    }
+
+    fn warning(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, path, message);
+    }
+
+    pub fn errors(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count()
+    }
+
+    /// Logs every issue, failing if any of them is an error.
+    pub fn check(&self) -> anyhow::Result<()> {
+        for issue in &self.issues {
+            match issue.severity {
+                Severity::Warning => warn!("Config warning at {issue}"),
+                Severity::Error => error!("Config error at {issue}"),
+            }
+        }
+
+        match self.errors() {
+            0 => Ok(()),
+            1 => bail!("the config has 1 error"),
+            errors => bail!("the config has {errors} errors"),
+        }
+    }
+}
+
+/// Checks everything that deserializes fine but can't work as configured.
+pub fn validate(config: &LureConfig) -> Report {
+    let mut report = Report::default();
+
+    for key in sorted_keys(&config.other_fields) {
+        report.warning(toml_key(key), "unknown key");
+    }
+
+    validate_listener(config, &mut report);
+    validate_limits(config, &mut report);
+    validate_usernames(config, &mut report);
+    validate_proxy(config, &mut report);
+    validate_routes(config, &mut report);
+    validate_files(config, &mut report);
+
+    for issue in &mut report.issues {
+        issue.source = config
+            .sources
+            .iter()
+            .find(|(key, _)| {
+                issue.path == **key || issue.path.starts_with(&format!("{key}."))
+            })
+            .map(|(_, source)| source.to_owned());
+    }
+
+    report
+}
+
+fn validate_listener(config: &LureConfig, report: &mut Report) {
+    if config.listener.is_empty() {
+        report.error("listener", "at least one listener is required");
+    }
+
+    let mut binds = HashMap::new();
+    for (i, listener) in config.listener.iter().enumerate() {
+        let path = format!("listener[{i}]");
+
+        match listener.bind.parse::<SocketAddr>() {
+            Ok(bind) => {
+                if let Some(other) = binds.insert(bind, i) {
+                    report.error(
+                        format!("{path}.bind"),
+                        format!("is also the bind of listener[{other}]"),
+                    );
+                }
+            }
+            Err(_) => report.error(
+                format!("{path}.bind"),
+                format!("invalid address '{}', expected ip:port", listener.bind),
+            ),
+        }
+
+        if listener.max_connections == 0 {
+            report.error(format!("{path}.max_connections"), "must be at least 1");
+        }
+
+        for (name, entries) in [("allow", &listener.allow), ("deny", &listener.deny)] {
+            for (j, entry) in entries.iter().enumerate() {
+                if parse_ip_net(entry).is_none() {
+                    report.error(
+                        format!("{path}.{name}[{j}]"),
+                        format!("invalid IP or CIDR range '{entry}'"),
+                    );
+                }
+            }
+        }
+
+        for (j, host) in listener.hosts.iter().enumerate() {
+            if let Err(e) = HostPattern::parse(host) {
+                report.error(format!("{path}.hosts[{j}]"), format!("{e:#}"));
+            }
+        }
+
+        for (j, server) in listener.servers.iter().enumerate() {
+            if !config.servers.contains_key(server) {
+                report.error(
+                    format!("{path}.servers[{j}]"),
+                    format!("refers to unknown server '{server}'"),
+                );
+            }
+        }
+    }
+}
+
+fn validate_limits(config: &LureConfig, report: &mut Report) {
+    let timeouts = &config.timeouts;
+    for (name, value) in [
+        ("handshake_ms", timeouts.handshake_ms),
+        ("status_ms", timeouts.status_ms),
+        ("login_ms", timeouts.login_ms),
+        ("play_idle_ms", timeouts.play_idle_ms),
+        ("connect_ms", timeouts.connect_ms),
+    ] {
+        if value == 0 {
+            report.error(format!("timeouts.{name}"), "must be at least 1");
+        }
+    }
+
+    let limits = &config.limits;
+    for (name, limits) in [
+        ("handshake", &limits.handshake),
+        ("status", &limits.status),
+        ("login", &limits.login),
+        ("play", &limits.play),
+    ] {
+        validate_packet_limits(&format!("limits.{name}"), limits, report);
+    }
+
+    let rate_limit = &config.rate_limit;
+    if rate_limit.enabled {
+        validate_budget("rate_limit.packets", &rate_limit.packets, report);
+        validate_budget("rate_limit.bytes", &rate_limit.bytes, report);
+
+        for name in sorted_keys(&rate_limit.overrides) {
+            let path = format!("rate_limit.overrides.{}", toml_key(name));
+            validate_budget(&path, &rate_limit.overrides[name], report);
+            if packets::packet_id(PROTOCOL_VERSION, name).is_none() {
+                report.warning(path, format!("unknown serverbound packet '{name}'"));
+            }
+        }
+    }
+
+    let throttle = &config.throttle;
+    let window_limits = [
+        throttle.connections_per_window,
+        throttle.status_per_window,
+        throttle.logins_per_window,
+    ];
+    if throttle.window_ms == 0 && window_limits.iter().any(|limit| *limit != 0) {
+        report.error(
+            "throttle.window_ms",
+            "must be at least 1 while any per-window limit is set",
+        );
+    }
+
+    let antibot = &config.antibot;
+    if antibot.mode != AntiBotMode::Off
+        && antibot.reconnect_timeout_secs.saturating_mul(1000) <= antibot.reconnect_min_delay_ms
+    {
+        report.error(
+            "antibot.reconnect_timeout_secs",
+            "must be longer than antibot.reconnect_min_delay_ms",
+        );
+    }
+}
+
+fn validate_packet_limits(path: &str, limits: &PacketLimits, report: &mut Report) {
+    for (name, value) in [
+        ("max_frame_size", limits.max_frame_size),
+        ("max_decompressed_size", limits.max_decompressed_size),
+    ] {
+        if value == 0 {
+            report.error(format!("{path}.{name}"), "must be at least 1");
+        } else if value > MAX_PACKET_SIZE as usize {
+            report.warning(
+                format!("{path}.{name}"),
+                format!("is above the protocol maximum of {MAX_PACKET_SIZE} bytes"),
+            );
+        }
+    }
+}
+
+fn validate_budget(path: &str, budget: &RateBudget, report: &mut Report) {
+    if budget.per_second == 0 {
+        report.error(format!("{path}.per_second"), "must be at least 1");
+    }
+}
+
+fn validate_usernames(config: &LureConfig, report: &mut Report) {
+    let usernames = &config.usernames;
+
+    if usernames.min_length > usernames.max_length {
+        report.error(
+            "usernames.min_length",
+            format!(
+                "is greater than usernames.max_length ({})",
+                usernames.max_length
+            ),
+        );
+    }
+
+    if !usernames.pattern.is_empty() {
+        if let Err(e) = Regex::new(&usernames.pattern) {
+            report.error("usernames.pattern", format!("invalid pattern: {e}"));
+        }
+    }
+}
+
+fn validate_proxy(config: &LureConfig, report: &mut Report) {
+    let favicon = Path::new(&config.proxy.favicon);
+    if favicon.is_file() {
+        match fs::read(favicon) {
+            Ok(bytes) => {
+                if image::guess_format(&bytes).is_err() {
+                    report.warning("proxy.favicon", "not a supported image format");
+                }
+            }
+            Err(e) => report.warning("proxy.favicon", format!("can't be read: {e}")),
+        }
+    }
+
+    let protocol = &config.protocol;
+    if protocol.min_version > protocol.max_version {
+        report.error(
+            "protocol.min_version",
+            format!(
+                "is greater than protocol.max_version ({})",
+                protocol.max_version
+            ),
+        );
+    }
+
+    for hostname in sorted_keys(&protocol.hosts) {
+        let range = &protocol.hosts[hostname];
+        if range.min_version > range.max_version {
+            report.error(
+                format!("protocol.hosts.{}.min_version", toml_key(hostname)),
+                format!("is greater than max_version ({})", range.max_version),
+            );
+        }
+    }
+}
+
+fn validate_routes(config: &LureConfig, report: &mut Report) {
+    let mut hostnames = HashMap::new();
+
+    for key in sorted_keys(&config.hosts) {
+        let path = format!("hosts.{}", toml_key(key));
+        let server = &config.hosts[key];
+
+        let pattern = match HostPattern::parse(key) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                report.error(path, format!("{e:#}"));
+                continue;
+            }
+        };
+
+        // Regular expressions may pick the server from their captures, which
+        // is only known once a hostname matches.
+        let templated = matches!(pattern, HostPattern::Regex(_)) && server.contains('$');
+        if !templated && !config.servers.contains_key(server) {
+            report.error(&path, format!("refers to unknown server '{server}'"));
+        }
+
+        let hostname = match pattern {
+            HostPattern::Exact(hostname) => hostname,
+            HostPattern::Suffix(suffix) => format!("*{suffix}"),
+            HostPattern::Regex(_) | HostPattern::Any => continue,
+        };
+        if let Some(other) = hostnames.insert(hostname, key) {
+            report.error(
+                path,
+                format!("matches the same hostnames as hosts.{}", toml_key(other)),
+            );
+        }
+    }
+
+    let binds: Vec<SocketAddr> = config
+        .listener
+        .iter()
+        .filter_map(|listener| listener.bind.parse().ok())
+        .collect();
+
+    for name in sorted_keys(&config.servers) {
+        let server = &config.servers[name];
+        let path = format!("servers.{}", toml_key(name));
+
+        match server.address.parse() {
+            Err(e) => report.error(format!("{path}.address"), e),
+            Ok(BackendAddress::Socket(address)) if binds.contains(&address) => report.error(
+                format!("{path}.address"),
+                "is an address Lure listens on (listener.bind)",
+            ),
+            Ok(BackendAddress::Socket(address)) if address.port() == 0 => {
+                report.error(format!("{path}.address"), "port must not be 0")
+            }
+            Ok(BackendAddress::Host(_, 0)) => {
+                report.error(format!("{path}.address"), "port must not be 0")
+            }
+            Ok(_) => {}
+        }
+
+        let forwarding = server
+            .forwarding
+            .unwrap_or(config.proxy.player_forward_mode);
+        match (forwarding, &server.secret) {
+            (ForwardingMode::Bungeeguard, None) => report.error(
+                format!("{path}.secret"),
+                "is required for bungeeguard forwarding",
+            ),
+            (ForwardingMode::None | ForwardingMode::Bungeecord, Some(_)) => report.warning(
+                format!("{path}.secret"),
+                "is only used with bungeeguard forwarding",
+            ),
+            _ => {}
+        }
+    }
+}
+
+fn validate_files(config: &LureConfig, report: &mut Report) {
+    let restricted_servers = config.servers.values().any(|server| server.restricted);
+    if config.whitelist.is_used() || restricted_servers {
+        validate_file("whitelist.file", &config.whitelist.file, report);
+    }
+
+    validate_file("bans.file", &config.bans.file, report);
+    validate_file("deny_list.file", &config.deny_list.file, report);
+}
+
+/// Files that don't exist yet are created on start, so only their directory
+/// has to exist.
+fn validate_file(path: &str, file: &str, report: &mut Report) {
+    let file = Path::new(file);
+
+    if file.exists() {
+        if let Err(e) = fs::read(file) {
+            report.error(path, format!("can't read {}: {e}", file.display()));
+        }
+        return;
+    }
+
+    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    if dir.is_some_and(|dir| !dir.is_dir()) {
+        report.error(
+            path,
+            format!("directory of {} does not exist", file.display()),
+        );
+    }
+}
+
+fn sorted_keys<V>(map: &HashMap<String, V>) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+
+    #[test]
+    fn validation_collects_every_issue() {
+        let mut config = LureConfig::default();
+        config.listener[0].bind = "localhost".to_string();
+        config.timeouts.login_ms = 0;
+        config
+            .hosts
+            .insert("play.example.com".to_string(), "survival".to_string());
+        config.servers.insert(
+            "hub".to_string(),
+            ServerConfig {
+                forwarding: Some(ForwardingMode::Bungeeguard),
+                ..ServerConfig::new("127.0.0.1:25566")
+            },
+        );
+
+        let report = validate(&config);
+        let paths: Vec<&str> = report
+            .issues
+            .iter()
+            .map(|issue| issue.path.as_str())
+            .collect();
+
+        assert_eq!(report.errors(), 4);
+        assert_eq!(
+            paths,
+            [
+                "listener[0].bind",
+                "timeouts.login_ms",
+                "hosts.\"play.example.com\"",
+                "servers.hub.secret"
+            ]
+        );
+        assert!(validate(&LureConfig::default()).issues.is_empty());
+
+        let config: LureConfig =
+            toml::from_str("servers = { lobby = \"lobby:port\", hub = \"h u b\" }").unwrap();
+        let report = validate(&config);
+        let paths: Vec<&str> = report
+            .issues
+            .iter()
+            .map(|issue| issue.path.as_str())
+            .collect();
+        assert_eq!(paths, ["servers.hub.address", "servers.lobby.address"]);
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use valence::prelude::*;
+
+use crate::utils::file_modified;
+
+/// Entry of a whitelist file, in the same format as the vanilla
+/// `whitelist.json`. Either field may be omitted.
+#[derive(Debug, Deserialize)]
+struct WhitelistEntry {
+    uuid: Option<Uuid>,
+    name: Option<String>,
+}
+
+/// Players allowed to join, reloaded whenever the whitelist file changes.
+#[derive(Debug)]
+pub struct Whitelist {
+    path: PathBuf,
+    inner: RwLock<WhitelistEntries>,
+}
+
+#[derive(Debug, Default)]
+struct WhitelistEntries {
+    modified: Option<SystemTime>,
+    uuids: HashSet<Uuid>,
+    /// Lowercase usernames.
+    names: HashSet<String>,
+}
+
+impl Whitelist {
+    pub fn new(path: &str) -> Self {
+        let whitelist = Self {
+            path: PathBuf::from(path),
+            inner: RwLock::new(WhitelistEntries::default()),
+        };
+
+        if !whitelist.path.exists() {
+            println!(
+                "Whitelist file {} does not exist, creating an empty one.",
+                whitelist.path.display()
+            );
+            if let Err(e) = fs::write(&whitelist.path, "[]\n") {
+                eprintln!("Failed to create whitelist {}: {e}", whitelist.path.display());
+            }
+        }
+
+        whitelist.refresh();
+        whitelist
+    }
+
+    /// Reloads the whitelist if the file was modified since the last check.
+    /// The previous entries are kept if the new file can't be parsed.
+    pub fn refresh(&self) {
+        let modified = file_modified(&self.path);
+        if self.inner.read().unwrap().modified == modified {
+            return;
+        }
+
+        let entries: Vec<WhitelistEntry> = match fs::read_to_string(&self.path)
+            .map_err(anyhow::Error::from)
+            .and_then(|raw| Ok(serde_json::from_str(&raw)?))
+        {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Failed to load whitelist {}: {e}", self.path.display());
+                self.inner.write().unwrap().modified = modified;
+                return;
+            }
+        };
+
+        let mut lck = self.inner.write().unwrap();
+        lck.modified = modified;
+        lck.uuids = entries.iter().filter_map(|entry| entry.uuid).collect();
+        lck.names = entries
+            .iter()
+            .filter_map(|entry| entry.name.as_ref())
+            .map(|name| name.to_lowercase())
+            .collect();
+
+        println!(
+            "Loaded {} whitelist entries from {}.",
+            entries.len(),
+            self.path.display()
+        );
+    }
+
+    pub fn contains(&self, uuid: Uuid, username: &str) -> bool {
+        let lck = self.inner.read().unwrap();
+        lck.uuids.contains(&uuid) || lck.names.contains(&username.to_lowercase())
+    }
+}